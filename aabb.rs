@@ -0,0 +1,120 @@
+use coords::BlockPos;
+use world::World;
+
+use common::*;
+use core::float;
+
+// Axis-aligned bounding box
+pub struct Aabb {
+    min: Vec3f,
+    max: Vec3f
+}
+
+fn axis_vec(axis: uint, d: float) -> Vec3f {
+    match axis {
+        0 => Vec3f::new(d, 0.0, 0.0),
+        1 => Vec3f::new(0.0, d, 0.0),
+        _ => Vec3f::new(0.0, 0.0, d)
+    }
+}
+
+pub impl Aabb {
+    fn new(min: Vec3f, max: Vec3f) -> Aabb {
+        Aabb { min: min, max: max }
+    }
+
+    // a box of the given width and height standing on pos
+    fn from_feet(pos: &Vec3f, width: float, height: float) -> Aabb {
+        let w = width / 2.0;
+        Aabb::new(Vec3f::new(pos.x - w, pos.y, pos.z - w),
+                  Vec3f::new(pos.x + w, pos.y + height, pos.z + w))
+    }
+
+    fn block(pos: BlockPos) -> Aabb {
+        Aabb::new(pos.to_vec(), pos.to_vec().add_v(&Vec3f::new(1.0, 1.0, 1.0)))
+    }
+
+    fn offset(&self, d: &Vec3f) -> Aabb {
+        Aabb::new(self.min.add_v(d), self.max.add_v(d))
+    }
+
+    // the box made larger by d on every side
+    fn grow(&self, d: float) -> Aabb {
+        Aabb::new(self.min.sub_v(&Vec3f::new(d, d, d)), self.max.add_v(&Vec3f::new(d, d, d)))
+    }
+
+    // grows the box to cover everything it passes through when moved by d
+    fn stretch(&self, d: &Vec3f) -> Aabb {
+        Aabb::new(Vec3f::new(self.min.x + float::min(d.x, 0.0), self.min.y + float::min(d.y, 0.0),
+                             self.min.z + float::min(d.z, 0.0)),
+                  Vec3f::new(self.max.x + float::max(d.x, 0.0), self.max.y + float::max(d.y, 0.0),
+                             self.max.z + float::max(d.z, 0.0)))
+    }
+
+    fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x && self.max.x > other.min.x &&
+        self.min.y < other.max.y && self.max.y > other.min.y &&
+        self.min.z < other.max.z && self.max.z > other.min.z
+    }
+
+    // every block the box overlaps
+    fn each_block(&self, f: &fn(BlockPos) -> bool) {
+        let (lo, hi) = (BlockPos::from_vec(&self.min), BlockPos::from_vec(&self.max));
+        for int::range(lo.y, hi.y + 1) |y| {
+            for int::range(lo.x, hi.x + 1) |x| {
+                for int::range(lo.z, hi.z + 1) |z| {
+                    if !f(BlockPos::new(x, y, z)) { return }
+                }
+            }
+        }
+    }
+
+    /* Shortens a movement of d along the axis (0 = x, 1 = y, 2 = z) so that this box stops
+     * at the face of other instead of entering it.
+     */
+    fn clip(&self, other: &Aabb, axis: uint, d: float) -> float {
+        for uint::range(0, 3) |i| {
+            if i != axis && (other.max[i] <= self.min[i] || other.min[i] >= self.max[i]) {
+                return d
+            }
+        }
+
+        if d > 0.0 && other.min[axis] >= self.max[axis] {
+            float::min(d, other.min[axis] - self.max[axis])
+        } else if d < 0.0 && other.max[axis] <= self.min[axis] {
+            float::max(d, other.max[axis] - self.min[axis])
+        } else {
+            d
+        }
+    }
+}
+
+/* Moves the box by delta through the world one axis at a time, stopping at solid blocks.
+ * Returns the movement actually made and whether it was cut short on each axis.
+ */
+pub fn move_box(world: &World, b: &Aabb, delta: &Vec3f) -> (Vec3f, (bool, bool, bool)) {
+    let boxes = world.collision_boxes(&b.stretch(delta));
+
+    // y goes first; of the horizontal axes the one moved along more goes first, so that
+    // sliding into a corner keeps going the way the player is pointing at more
+    let order = if float::abs(delta.x) > float::abs(delta.z) { [1u, 0, 2] } else { [1u, 2, 0] };
+
+    let mut current = *b;
+    let mut moved = [0.0, 0.0, 0.0];
+    for order.each |&axis| {
+        let mut d = delta[axis];
+        for boxes.each |other| {
+            d = current.clip(other, axis, d);
+        }
+        current = current.offset(&axis_vec(axis, d));
+        moved[axis] = d;
+    }
+
+    (Vec3f::new(moved[0], moved[1], moved[2]),
+     (moved[0] != delta.x, moved[1] != delta.y, moved[2] != delta.z))
+}
+
+// whether the box overlaps anything solid in the world
+pub fn collides(world: &World, b: &Aabb) -> bool {
+    world.collision_boxes(b).any(|other| b.intersects(other))
+}
@@ -1,21 +1,52 @@
 use common::*;
+use coords::LocalPos;
 use glcore::*;
 use buffer::Buffer;
 use shader::Program;
+use core::util;
+use core::float;
+use entity::Entity;
+use fluid;
+use item::{ToolClass, ToolTier, Pickaxe, Shovel, Axe, WoodenTier, StoneTier, IronTier};
 
 #[deriving(Eq)]
 pub enum Block {
     Air,
     Grass,
     Stone,
-    Dirt
+    Dirt,
+    Bedrock,
+    // fluids carry their flow level, 0 being a source block
+    Water(u8),
+    Lava(u8),
+    CoalOre,
+    IronOre,
+    GoldOre,
+    DiamondOre,
+    Cobblestone,
+    Wood,
+    Leaves,
+    Planks,
+    // crops carry their growth stage
+    Wheat(u8),
+    Torch,
+    Sand,
+    Gravel,
+    // half a block high, sitting on the bottom of its cell
+    Slab,
+    // carries whether it's open; open doors can be walked through
+    Door(bool)
 }
 
 pub impl Block {
     fn blocks(&self) -> bool {
         match *self {
-            Air => false,
-            Grass | Stone | Dirt => true
+            Air | Water(_) | Lava(_) | Wheat(_) | Torch => false,
+            Grass | Stone | Dirt | Bedrock => true,
+            CoalOre | IronOre | GoldOre | DiamondOre => true,
+            Cobblestone | Wood | Leaves | Planks => true,
+            Sand | Gravel | Slab => true,
+            Door(open) => !open
         }
     }
 
@@ -24,7 +55,20 @@ pub impl Block {
             Air => 0,
             Grass => 0,
             Stone => 1,
-            Dirt => 2
+            Dirt => 2,
+            Bedrock => 1,
+            Water(_) => 4,
+            Lava(_) => 5,
+            CoalOre | IronOre | GoldOre | DiamondOre => 1,
+            Cobblestone => 1,
+            Wood | Planks => 2,
+            Leaves => 0,
+            Wheat(_) => 0,
+            Torch => 2,
+            Sand => 2,
+            Gravel => 1,
+            Slab => 1,
+            Door(_) => 2
         }
     }
 
@@ -33,12 +77,182 @@ pub impl Block {
             Air => 0,
             Grass => 3,
             Stone => 1,
-            Dirt => 2
+            Dirt => 2,
+            Bedrock => 1,
+            Water(_) => 4,
+            Lava(_) => 5,
+            CoalOre | IronOre | GoldOre | DiamondOre => 1,
+            Cobblestone => 1,
+            Wood | Planks => 2,
+            Leaves => 0,
+            Wheat(_) => 0,
+            Torch => 2,
+            Sand => 2,
+            Gravel => 1,
+            Slab => 1,
+            Door(_) => 2
         }
     }
 
-    fn breaking_time(&self) -> float {
-        0.5
+    // height of the solid part of the block, 0 for blocks that can be walked through
+    fn collision_height(&self) -> float {
+        match *self {
+            Slab => 0.5,
+            b if b.blocks() => 1.0,
+            _ => 0.0
+        }
+    }
+
+    // whether the block keeps light from reaching the block below
+    fn is_opaque(&self) -> bool {
+        match *self {
+            Leaves | Slab | Door(_) => false,
+            b => b.blocks()
+        }
+    }
+
+    // protected blocks can't be changed by World::replace_block
+    fn is_protected(&self) -> bool {
+        *self == Bedrock
+    }
+
+    // whether the block pops off when the block below it stops being solid
+    fn needs_support(&self) -> bool {
+        match *self {
+            Torch | Wheat(_) => true,
+            _ => false
+        }
+    }
+
+    // whether the block turns into a falling block when there is nothing solid below it
+    fn falls(&self) -> bool {
+        match *self {
+            Sand | Gravel => true,
+            _ => false
+        }
+    }
+
+    fn is_fluid(&self) -> bool {
+        match *self {
+            Water(_) | Lava(_) => true,
+            _ => false
+        }
+    }
+
+    /* How hard the block is to break, with no unit of its own: item::mining_time scales it by
+     * 1.5, or 5 without the required tool, and divides by the tool's speed to get seconds
+     */
+    fn hardness(&self) -> float {
+        match *self {
+            Air | Water(_) | Lava(_) | Wheat(_) | Torch => 0.0,
+            Leaves => 0.2,
+            Grass | Dirt | Sand => 0.5,
+            Gravel => 0.6,
+            Stone | Cobblestone | Slab => 1.5,
+            CoalOre | IronOre | GoldOre | DiamondOre => 3.0,
+            Wood | Planks | Door(_) => 2.0,
+            Bedrock => float::infinity
+        }
+    }
+
+    // the tool that breaks the block faster
+    fn tool_class(&self) -> Option<ToolClass> {
+        match *self {
+            Stone | Cobblestone | Slab | CoalOre | IronOre | GoldOre | DiamondOre => Some(Pickaxe),
+            Grass | Dirt | Sand | Gravel => Some(Shovel),
+            Wood | Planks | Door(_) => Some(Axe),
+            _ => None
+        }
+    }
+
+    // the tool and lowest tier of it that the block has to be broken with to drop anything
+    fn required_tool(&self) -> Option<(ToolClass, ToolTier)> {
+        match *self {
+            Stone | Cobblestone | Slab | CoalOre => Some((Pickaxe, WoodenTier)),
+            IronOre => Some((Pickaxe, StoneTier)),
+            GoldOre | DiamondOre => Some((Pickaxe, IronTier)),
+            _ => None
+        }
+    }
+
+    // (id, data) pair used when saving chunks
+    fn to_id(&self) -> (u8, u8) {
+        match *self {
+            Air => (0, 0),
+            Grass => (1, 0),
+            Stone => (2, 0),
+            Dirt => (3, 0),
+            Bedrock => (4, 0),
+            Water(l) => (5, l),
+            Lava(l) => (6, l),
+            CoalOre => (7, 0),
+            IronOre => (8, 0),
+            GoldOre => (9, 0),
+            DiamondOre => (10, 0),
+            Cobblestone => (11, 0),
+            Wood => (12, 0),
+            Leaves => (13, 0),
+            Planks => (14, 0),
+            Wheat(s) => (15, s),
+            Torch => (16, 0),
+            Sand => (17, 0),
+            Gravel => (18, 0),
+            Slab => (19, 0),
+            Door(open) => (20, open as u8)
+        }
+    }
+
+    fn from_id(id: u8, data: u8) -> Option<Block> {
+        Some(match id {
+            0 => Air,
+            1 => Grass,
+            2 => Stone,
+            3 => Dirt,
+            4 => Bedrock,
+            5 => Water(data),
+            6 => Lava(data),
+            7 => CoalOre,
+            8 => IronOre,
+            9 => GoldOre,
+            10 => DiamondOre,
+            11 => Cobblestone,
+            12 => Wood,
+            13 => Leaves,
+            14 => Planks,
+            15 => Wheat(data),
+            16 => Torch,
+            17 => Sand,
+            18 => Gravel,
+            19 => Slab,
+            20 => Door(data != 0),
+            _ => return None
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Air => "air",
+            Grass => "grass",
+            Stone => "stone",
+            Dirt => "dirt",
+            Bedrock => "bedrock",
+            Water(_) => "water",
+            Lava(_) => "lava",
+            CoalOre => "coal ore",
+            IronOre => "iron ore",
+            GoldOre => "gold ore",
+            DiamondOre => "diamond ore",
+            Cobblestone => "cobblestone",
+            Wood => "wood",
+            Leaves => "leaves",
+            Planks => "planks",
+            Wheat(_) => "wheat",
+            Torch => "torch",
+            Sand => "sand",
+            Gravel => "gravel",
+            Slab => "slab",
+            Door(_) => "door"
+        }
     }
 }
 
@@ -49,9 +263,20 @@ struct BufferCache {
     vertex_no: uint
 }
 
+// A pending block update; pos is chunk-local
+pub struct ScheduledTick {
+    pos: LocalPos,
+    due: u64
+}
+
 // 16x16x16 chunk
 pub struct Chunk {
     blocks: [Block, ..16*16*16],
+    scheduled_ticks: ~[ScheduledTick],
+    // entities whose position is inside the chunk
+    entities: ~[Entity],
+    // whether the blocks changed since the buffer cache was last built
+    dirty: bool,
     buffer_cache: Option<BufferCache>
 }
 
@@ -59,30 +284,93 @@ pub impl Chunk {
     fn new() -> Chunk {
         Chunk {
             blocks: [Stone, ..16*16*16],
+            scheduled_ticks: ~[],
+            entities: ~[],
+            dirty: true,
             buffer_cache: None
         }
     }
 
-    fn block_at_vec(&self, pos: &Vec3f) -> Option<&'self Block> {
-        let cc = (pos.x, pos.y, pos.z).floor();
+    fn schedule_tick(&mut self, pos: LocalPos, due: u64) {
+        if self.scheduled_ticks.any(|t| t.pos == pos) { return }
 
-        self.block_at(cc)
+        self.scheduled_ticks.push(ScheduledTick { pos: pos, due: due });
     }
 
-    fn block_at(&self, cc: (int, int, int)) -> Option<&'self Block> {
-        let (x, y, z) = cc;
+    // removes and returns the positions of all ticks due at or before now
+    fn take_due_ticks(&mut self, now: u64) -> ~[LocalPos] {
+        let mut due = ~[];
+        let mut later = ~[];
+        for util::replace(&mut self.scheduled_ticks, ~[]).each |&t| {
+            if t.due <= now { due.push(t.pos) } else { later.push(t) }
+        }
+        self.scheduled_ticks = later;
+        due
+    }
 
-        if x < 0 || x > 15 || y < 0 || y > 15 || z < 0 || z > 15 { return None }
+    /* Scheduled ticks are stored relative to now, so that they survive the world
+     * clock starting over. Entities come last.
+     */
+    fn write(&self, w: @io::Writer, now: u64) {
+        for self.blocks.each |b| {
+            let (id, data) = b.to_id();
+            w.write_u8(id);
+            w.write_u8(data);
+        }
 
-        Some(&self.blocks[y*16*16+z*16+x])
+        w.write_be_u32(self.scheduled_ticks.len() as u32);
+        for self.scheduled_ticks.each |t| {
+            w.write_u8(t.pos.x as u8);
+            w.write_u8(t.pos.y as u8);
+            w.write_u8(t.pos.z as u8);
+            w.write_be_u64(if t.due > now { t.due - now } else { 0 });
+        }
+
+        w.write_be_u32(self.entities.len() as u32);
+        for self.entities.each |e| {
+            e.write(w);
+        }
+    }
+
+    fn read(r: @io::Reader, now: u64) -> Option<Chunk> {
+        let mut c = Chunk::new();
+
+        for uint::range(0, 16*16*16) |i| {
+            let (id, data) = (r.read_byte(), r.read_byte());
+            if data < 0 { return None }
+            match Block::from_id(id as u8, data as u8) {
+                Some(b) => c.blocks[i] = b,
+                None => return None
+            }
+        }
+
+        for (r.read_be_u32() as uint).times {
+            let (x, y, z) = (r.read_u8() as int, r.read_u8() as int, r.read_u8() as int);
+            let delay = r.read_be_u64();
+            c.scheduled_ticks.push(ScheduledTick { pos: LocalPos::new(x, y, z), due: now + delay });
+        }
+
+        // chunks saved before entities were stored end here
+        if r.eof() { return Some(c) }
+        for (r.read_be_u32() as uint).times {
+            match Entity::read(r) {
+                Some(e) => c.entities.push(e),
+                None => return None
+            }
+        }
+        Some(c)
     }
 
-    fn block_at_mut(&mut self, cc: (int, int, int)) -> Option<&'self mut Block> {
-        let (x, y, z) = cc;
+    fn block_at(&self, pos: LocalPos) -> Option<&'self Block> {
+        if !pos.is_valid() { return None }
 
-        if x < 0 || x > 15 || y < 0 || y > 15 || z < 0 || z > 15 { return None }
+        Some(&self.blocks[pos.index()])
+    }
+
+    fn block_at_mut(&mut self, pos: LocalPos) -> Option<&'self mut Block> {
+        if !pos.is_valid() { return None }
 
-        Some(&mut self.blocks[y*16*16+z*16+x])
+        Some(&mut self.blocks[pos.index()])
     }
 
     // x,z is the horizontal plane
@@ -120,7 +408,28 @@ pub impl Chunk {
                 Air => loop,
                 _ => ()
             }
-            vbuf.push_all_move(make_cube(x as float+0.5,y as float+0.5,z as float+0.5,0.5));
+            if block.is_fluid() {
+                let (v, t, n) = self.fluid_faces(x as int, y as int, z as int, block);
+                vbuf.push_all_move(v);
+                tbuf.push_all_move(t);
+                nbuf.push_all_move(n);
+                loop
+            }
+            let h = block.collision_height();
+            if block == Door(true) {
+                // swung open against the side of its cell
+                vbuf.push_all_move(make_box(x as float+0.5, y as float+0.5, z as float+0.1,
+                                            0.5, 0.5, 0.1));
+            } else if block == Torch {
+                // a stick standing in the middle of its cell
+                vbuf.push_all_move(make_box(x as float+0.5, y as float+0.3, z as float+0.5,
+                                            0.0625, 0.3, 0.0625));
+            } else if h > 0.0 && h < 1.0 {
+                vbuf.push_all_move(make_box(x as float+0.5, y as float+h/2.0, z as float+0.5,
+                                            0.5, h/2.0, 0.5));
+            } else {
+                vbuf.push_all_move(make_cube(x as float+0.5,y as float+0.5,z as float+0.5,0.5));
+            }
             tbuf.push_all_move(make_cube_texcoord(block.top_texture_id(), block.side_texture_id()));
             nbuf.push_all_move(make_cube_normal());
         }
@@ -128,6 +437,33 @@ pub impl Chunk {
         (vbuf, tbuf, nbuf)
     }
 
+    /* A fluid block with its surface at the height of its level, or full height under more
+     * of the same fluid. Faces against the same fluid are left out, so that a lake is only
+     * drawn where it meets something else.
+     */
+    fn fluid_faces(&self, x: int, y: int, z: int, block: Block) -> (~[Vec3f], ~[Vec3f], ~[Vec3f]) {
+        let same = |dx: int, dy: int, dz: int| {
+            match self.block_at(LocalPos::new(x + dx, y + dy, z + dz)) {
+                Some(&b) => fluid::same_kind(b, block),
+                None => false
+            }
+        };
+        let h = if same(0, 1, 0) { 1.0 } else { fluid::surface_height(block) };
+
+        let v = make_box(x as float + 0.5, y as float + h / 2.0, z as float + 0.5,
+                         0.5, h / 2.0, 0.5);
+        let t = make_cube_texcoord(block.top_texture_id(), block.side_texture_id());
+        let n = make_cube_normal();
+        let (mut vbuf, mut tbuf, mut nbuf) = (~[], ~[], ~[]);
+        for face_offsets.eachi |i, &(dx, dy, dz)| {
+            if same(dx, dy, dz) { loop }
+            vbuf.push_all(v.slice(i * 4, i * 4 + 4));
+            tbuf.push_all(t.slice(i * 4, i * 4 + 4));
+            nbuf.push_all(n.slice(i * 4, i * 4 + 4));
+        }
+        (vbuf, tbuf, nbuf)
+    }
+
     fn update_buffer_cache(&mut self) {
         if self.buffer_cache.is_none() {
             self.buffer_cache = Some(BufferCache {
@@ -146,6 +482,7 @@ pub impl Chunk {
             },
             None => fail!(~"uninitialized buffer cache when populating")
         }
+        self.dirty = false;
     }
 
     fn draw_cached(&self, program: &mut Program) {
@@ -167,17 +504,85 @@ pub impl Chunk {
     }
 }
 
-pub fn make_cube(x: float, y: float, z: float, n: float) -> ~[Vec3f] {
+// layers in the block texture array, see Block::top_texture_id
+pub static TEXTURE_LAYERS: uint = 6;
+
+/* A unit cube uploaded once, for drawing blocks outside of chunks: item icons, entities and
+ * the parts of mobs. There's a texture coordinate buffer for every pair of top and side
+ * textures.
+ */
+pub struct BlockMesh {
+    position: Buffer,
+    normal: Buffer,
+    texcoords: ~[Buffer]
+}
+
+pub impl BlockMesh {
+    fn new() -> BlockMesh {
+        let mut position = Buffer::new();
+        let mut normal = Buffer::new();
+        position.update(make_cube(0.5, 0.5, 0.5, 0.5));
+        normal.update(make_cube_normal());
+
+        let mut texcoords = ~[];
+        for uint::range(0, TEXTURE_LAYERS) |top| {
+            for uint::range(0, TEXTURE_LAYERS) |side| {
+                let mut t = Buffer::new();
+                t.update(make_cube_texcoord(top, side));
+                texcoords.push(t);
+            }
+        }
+
+        BlockMesh { position: position, normal: normal, texcoords: texcoords }
+    }
+
+    // draws a single unit block with its corner at the origin of the current modelview
+    fn draw_block(&self, program: &mut Program, block: Block) {
+        let t = block.top_texture_id() * TEXTURE_LAYERS + block.side_texture_id();
+
+        program.bind();
+        program.set_attribute_vec3("position", &self.position);
+        program.set_attribute_vec3("normal",   &self.normal);
+        program.set_attribute_vec3("texcoord", &self.texcoords[t]);
+
+        glDrawArrays(GL_QUADS, 0, 24);
+    }
+
+    // draws boxes given by their centre and half-extents, each textured like a block
+    fn draw_boxes(&self, program: &mut Program, modelview: &Mat4f,
+                  boxes: &[(Vec3f, Vec3f, Block)])
+    {
+        for boxes.each |&(c, h, block)| {
+            let corner = c.sub_v(&h);
+            program.set_uniform_mat4("modelview", &modelview.mul_m(
+                &translation_matrix((corner.x, corner.y, corner.z))).mul_m(
+                &scale_matrix_xyz(2.0 * h.x, 2.0 * h.y, 2.0 * h.z)));
+            self.draw_block(program, block);
+        }
+    }
+}
+
+// the neighbour each face of make_box looks towards, in the order the faces are made
+static face_offsets: [(int, int, int), ..6] = [
+    (0, 1, 0), (0, -1, 0), (-1, 0, 0), (1, 0, 0), (0, 0, 1), (0, 0, -1)
+];
+
+// An axis-aligned box centred on (x, y, z) with half-extents n, m and k
+pub fn make_box(x: float, y: float, z: float, n: float, m: float, k: float) -> ~[Vec3f] {
     ~[
-        Vec3f::new(x-n,y+n,z-n), Vec3f::new(x-n,y+n,z+n), Vec3f::new(x+n,y+n,z+n), Vec3f::new(x+n,y+n,z-n),  // top
-        Vec3f::new(x-n,y-n,z-n), Vec3f::new(x+n,y-n,z-n), Vec3f::new(x+n,y-n,z+n), Vec3f::new(x-n,y-n,z+n),  // bottom
-        Vec3f::new(x-n,y-n,z-n), Vec3f::new(x-n,y-n,z+n), Vec3f::new(x-n,y+n,z+n), Vec3f::new(x-n,y+n,z-n),  // left
-        Vec3f::new(x+n,y-n,z+n), Vec3f::new(x+n,y-n,z-n), Vec3f::new(x+n,y+n,z-n), Vec3f::new(x+n,y+n,z+n),  // right
-        Vec3f::new(x-n,y-n,z+n), Vec3f::new(x+n,y-n,z+n), Vec3f::new(x+n,y+n,z+n), Vec3f::new(x-n,y+n,z+n),  // front
-        Vec3f::new(x+n,y-n,z-n), Vec3f::new(x-n,y-n,z-n), Vec3f::new(x-n,y+n,z-n), Vec3f::new(x+n,y+n,z-n),  // back
+        Vec3f::new(x-n,y+m,z-k), Vec3f::new(x-n,y+m,z+k), Vec3f::new(x+n,y+m,z+k), Vec3f::new(x+n,y+m,z-k),  // top
+        Vec3f::new(x-n,y-m,z-k), Vec3f::new(x+n,y-m,z-k), Vec3f::new(x+n,y-m,z+k), Vec3f::new(x-n,y-m,z+k),  // bottom
+        Vec3f::new(x-n,y-m,z-k), Vec3f::new(x-n,y-m,z+k), Vec3f::new(x-n,y+m,z+k), Vec3f::new(x-n,y+m,z-k),  // left
+        Vec3f::new(x+n,y-m,z+k), Vec3f::new(x+n,y-m,z-k), Vec3f::new(x+n,y+m,z-k), Vec3f::new(x+n,y+m,z+k),  // right
+        Vec3f::new(x-n,y-m,z+k), Vec3f::new(x+n,y-m,z+k), Vec3f::new(x+n,y+m,z+k), Vec3f::new(x-n,y+m,z+k),  // front
+        Vec3f::new(x+n,y-m,z-k), Vec3f::new(x-n,y-m,z-k), Vec3f::new(x-n,y+m,z-k), Vec3f::new(x+n,y+m,z-k),  // back
     ]
 }
 
+pub fn make_cube(x: float, y: float, z: float, n: float) -> ~[Vec3f] {
+    make_box(x, y, z, n, n, n)
+}
+
 pub fn make_cube_texcoord(tid: uint, tid2: uint) -> ~[Vec3f] {
     let tid = tid as float;
     let tid2 = tid2 as float;
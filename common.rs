@@ -6,15 +6,22 @@ pub use lmath::vec::Vec3f;
 pub use lmath::mat::Mat4f;
 pub use lmath::quat::Quatf;
 
-pub trait TripleFloor<Out> {
-    fn floor(&self) -> (Out,Out,Out);
+pub fn translation_matrix(t: (float, float, float)) -> Mat4f {
+    let (x,y,z) = t;
+    Mat4f::new(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, x, y, z, 1.0)
 }
 
-impl TripleFloor<int> for (float,float,float) {
-    fn floor(&self) -> (int,int,int) {
-        let (x,y,z) = *self;
-        (float::floor(x as f64) as int, float::floor(y as f64) as int, float::floor(z as f64) as int)
-    }
+pub fn scale_matrix(s: float) -> Mat4f {
+    Mat4f::new(s, 0.0, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0, 0.0, 1.0)
+}
+
+pub fn scale_matrix_xyz(x: float, y: float, z: float) -> Mat4f {
+    Mat4f::new(x, 0.0, 0.0, 0.0, 0.0, y, 0.0, 0.0, 0.0, 0.0, z, 0.0, 0.0, 0.0, 0.0, 1.0)
+}
+
+// the point t of the way from a to b
+pub fn mix(a: &Vec3f, b: &Vec3f, t: float) -> Vec3f {
+    a.add_v(&b.sub_v(a).mul_t(t))
 }
 
 pub trait TripleFloat {
@@ -0,0 +1,81 @@
+use core::hashmap::HashMap;
+use core::float;
+
+pub static CONFIG_FILE: &'static str = "settings.cfg";
+
+/* Calls f with the number and the trimmed text of each line that has anything left once
+ * comments, which run from a '#' to the end of the line, are taken out. Shared by the data
+ * files with line-based formats.
+ */
+pub fn each_content_line(text: &str, f: &fn(uint, &str) -> bool) {
+    let mut line_no = 0;
+    for str::each_line(text) |line| {
+        line_no += 1;
+        let line = match str::find_char(line, '#') {
+            Some(i) => line.slice(0, i),
+            None => line
+        }.trim();
+        if line.is_empty() { loop }
+        if !f(line_no, line) { return }
+    }
+}
+
+/* Settings read from a text file of "key = value" lines. Anything after a '#' is a comment.
+ * Missing files and keys fall back to the defaults given by the caller.
+ */
+pub struct Config {
+    values: HashMap<~str, ~str>
+}
+
+pub impl Config {
+    fn new() -> Config {
+        Config { values: HashMap::new() }
+    }
+
+    fn load(path: &path::Path) -> Config {
+        match io::read_whole_file_str(path) {
+            Ok(text) => Config::parse(text),
+            Err(_) => Config::new()
+        }
+    }
+
+    fn parse(text: &str) -> Config {
+        let mut config = Config::new();
+        for each_content_line(text) |_, line| {
+            match str::find_char(line, '=') {
+                Some(i) => {
+                    config.values.insert(line.slice(0, i).trim().to_owned(),
+                                         line.slice(i + 1, line.len()).trim().to_owned());
+                },
+                None => println(fmt!("config: ignoring line without '=': %s", line))
+            }
+        }
+        config
+    }
+
+    fn get_float(&self, key: &str, default: float) -> float {
+        match self.values.find(&key.to_owned()) {
+            Some(value) => match float::from_str(*value) {
+                Some(f) => f,
+                None => {
+                    println(fmt!("config: %s is not a number: %s", key, *value));
+                    default
+                }
+            },
+            None => default
+        }
+    }
+
+    fn get_int(&self, key: &str, default: int) -> int {
+        match self.values.find(&key.to_owned()) {
+            Some(value) => match int::from_str(*value) {
+                Some(i) => i,
+                None => {
+                    println(fmt!("config: %s is not a whole number: %s", key, *value));
+                    default
+                }
+            },
+            None => default
+        }
+    }
+}
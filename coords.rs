@@ -0,0 +1,207 @@
+use common::*;
+use core::float;
+
+pub static CHUNK_SIZE: int = 16;
+
+// Integer division and remainder rounding towards negative infinity, so that
+// div_floor(-1, 16) == -1 and mod_floor(-1, 16) == 15
+pub fn div_floor(a: int, b: int) -> int {
+    if a >= 0 {
+        a / b
+    } else {
+        // not a / b - 1, which puts exact multiples like -16 one chunk too far down
+        (a + 1) / b - 1
+    }
+}
+
+pub fn mod_floor(a: int, b: int) -> int {
+    a - div_floor(a, b) * b
+}
+
+// A block in the world. Also used for offsets between blocks, like face normals.
+#[deriving(Eq, IterBytes)]
+pub struct BlockPos {
+    x: int,
+    y: int,
+    z: int
+}
+
+// A chunk, in units of CHUNK_SIZE blocks
+#[deriving(Eq, IterBytes)]
+pub struct ChunkPos {
+    x: int,
+    y: int,
+    z: int
+}
+
+// A block inside its chunk; each component is in 0..CHUNK_SIZE when valid
+#[deriving(Eq, IterBytes)]
+pub struct LocalPos {
+    x: int,
+    y: int,
+    z: int
+}
+
+pub static neighbour_offsets: [BlockPos, ..6] = [
+    BlockPos { x:  1, y:  0, z:  0 }, BlockPos { x: -1, y:  0, z:  0 },
+    BlockPos { x:  0, y:  1, z:  0 }, BlockPos { x:  0, y: -1, z:  0 },
+    BlockPos { x:  0, y:  0, z:  1 }, BlockPos { x:  0, y:  0, z: -1 }
+];
+
+pub static horizontal_offsets: [BlockPos, ..4] = [
+    BlockPos { x:  1, y:  0, z:  0 }, BlockPos { x: -1, y:  0, z:  0 },
+    BlockPos { x:  0, y:  0, z:  1 }, BlockPos { x:  0, y:  0, z: -1 }
+];
+
+pub impl BlockPos {
+    fn new(x: int, y: int, z: int) -> BlockPos {
+        BlockPos { x: x, y: y, z: z }
+    }
+
+    // the block containing the point
+    fn from_vec(v: &Vec3f) -> BlockPos {
+        BlockPos::new(float::floor(v.x as f64) as int,
+                      float::floor(v.y as f64) as int,
+                      float::floor(v.z as f64) as int)
+    }
+
+    fn chunk(&self) -> ChunkPos {
+        ChunkPos::new(div_floor(self.x, CHUNK_SIZE), div_floor(self.y, CHUNK_SIZE),
+                      div_floor(self.z, CHUNK_SIZE))
+    }
+
+    fn local(&self) -> LocalPos {
+        LocalPos::new(mod_floor(self.x, CHUNK_SIZE), mod_floor(self.y, CHUNK_SIZE),
+                      mod_floor(self.z, CHUNK_SIZE))
+    }
+
+    fn offset(&self, dx: int, dy: int, dz: int) -> BlockPos {
+        BlockPos::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+
+    fn up(&self) -> BlockPos { self.offset(0, 1, 0) }
+    fn down(&self) -> BlockPos { self.offset(0, -1, 0) }
+
+    fn each_neighbour(&self, f: &fn(BlockPos) -> bool) {
+        for neighbour_offsets.each |d| {
+            if !f(*self + *d) { return }
+        }
+    }
+
+    fn each_horizontal_neighbour(&self, f: &fn(BlockPos) -> bool) {
+        for horizontal_offsets.each |d| {
+            if !f(*self + *d) { return }
+        }
+    }
+
+    // the minimum corner of the block
+    fn to_vec(&self) -> Vec3f {
+        Vec3f::new(self.x as float, self.y as float, self.z as float)
+    }
+
+    fn center(&self) -> Vec3f {
+        Vec3f::new(self.x as float + 0.5, self.y as float + 0.5, self.z as float + 0.5)
+    }
+}
+
+impl Add<BlockPos, BlockPos> for BlockPos {
+    fn add(&self, rhs: &BlockPos) -> BlockPos {
+        BlockPos::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub<BlockPos, BlockPos> for BlockPos {
+    fn sub(&self, rhs: &BlockPos) -> BlockPos {
+        BlockPos::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Neg<BlockPos> for BlockPos {
+    fn neg(&self) -> BlockPos {
+        BlockPos::new(-self.x, -self.y, -self.z)
+    }
+}
+
+pub impl ChunkPos {
+    fn new(x: int, y: int, z: int) -> ChunkPos {
+        ChunkPos { x: x, y: y, z: z }
+    }
+
+    // the block at local (0, 0, 0)
+    fn origin(&self) -> BlockPos {
+        BlockPos::new(self.x * CHUNK_SIZE, self.y * CHUNK_SIZE, self.z * CHUNK_SIZE)
+    }
+
+    fn block(&self, local: LocalPos) -> BlockPos {
+        self.origin().offset(local.x, local.y, local.z)
+    }
+
+    fn offset(&self, dx: int, dy: int, dz: int) -> ChunkPos {
+        ChunkPos::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+}
+
+pub impl LocalPos {
+    fn new(x: int, y: int, z: int) -> LocalPos {
+        LocalPos { x: x, y: y, z: z }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.x >= 0 && self.x < CHUNK_SIZE && self.y >= 0 && self.y < CHUNK_SIZE &&
+            self.z >= 0 && self.z < CHUNK_SIZE
+    }
+
+    // index into Chunk::blocks; y is the outermost axis
+    fn index(&self) -> uint {
+        (self.y * CHUNK_SIZE * CHUNK_SIZE + self.z * CHUNK_SIZE + self.x) as uint
+    }
+
+    fn offset(&self, dx: int, dy: int, dz: int) -> LocalPos {
+        LocalPos::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::*;
+    use coords::*;
+
+    // blocks either side of the chunk borders around the origin
+    static edges: [int, ..9] = [-32, -17, -16, -15, -1, 0, 15, 16, 17];
+
+    #[test]
+    fn floor_division() {
+        let expected = [(-2, 0), (-2, 15), (-1, 0), (-1, 1), (-1, 15), (0, 0), (0, 15), (1, 0),
+                        (1, 1)];
+        for edges.eachi |i, &a| {
+            let (div, rem) = expected[i];
+            assert_eq!(div_floor(a, CHUNK_SIZE), div);
+            assert_eq!(mod_floor(a, CHUNK_SIZE), rem);
+        }
+    }
+
+    #[test]
+    fn points_floor_to_their_block() {
+        let cases = [(-16.5, -17), (-16.0, -16), (-0.5, -1), (-0.0, 0), (0.5, 0), (15.99, 15),
+                     (16.0, 16)];
+        for cases.each |&(v, expected)| {
+            let pos = BlockPos::from_vec(&Vec3f::new(v, v, v));
+            assert!(pos == BlockPos::new(expected, expected, expected));
+        }
+    }
+
+    #[test]
+    fn chunk_and_local_round_trip() {
+        for edges.each |&x| {
+            for edges.each |&y| {
+                for edges.each |&z| {
+                    let pos = BlockPos::new(x, y, z);
+                    let (cc, local) = (pos.chunk(), pos.local());
+                    assert!(local.is_valid());
+                    assert!(cc.block(local) == pos);
+                    assert!(cc.origin().chunk() == cc);
+                }
+            }
+        }
+    }
+}
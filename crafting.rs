@@ -0,0 +1,332 @@
+use config;
+use item::{Item, ItemStack};
+use registry::Registry;
+
+use core::util;
+
+pub static RECIPE_FILE: &'static str = "recipes.txt";
+
+pub enum Pattern {
+    // ingredients in fixed positions: width, height and the cells row by row
+    Shaped(uint, uint, ~[Option<Item>]),
+    // ingredients in any position
+    Shapeless(~[Item])
+}
+
+pub struct Recipe {
+    pattern: Pattern,
+    result: ItemStack
+}
+
+/* Parses recipes, one per line:
+ *
+ *     shapeless 4 planks <- wood
+ *     shaped 4 stick <- p/p with p=planks
+ *
+ * Rows of a shaped pattern are separated by '/', with '.' for an empty cell, and each
+ * letter is given an item after "with". Anything after a '#' is a comment.
+ */
+pub fn parse_recipes(text: &str, registry: &Registry) -> Result<~[Recipe], ~str> {
+    let mut recipes = ~[];
+
+    for config::each_content_line(text) |line_no, line| {
+        let words = str::words(line);
+        match parse_recipe(words, registry) {
+            Ok(recipe) => recipes.push(recipe),
+            Err(e) => return Err(fmt!("line %u: %s", line_no, e))
+        }
+    }
+
+    Ok(recipes)
+}
+
+fn parse_recipe(words: &[~str], registry: &Registry) -> Result<Recipe, ~str> {
+    if words.len() < 5 || words[3] != ~"<-" {
+        return Err(~"expected '<kind> <count> <result> <- <ingredients>'")
+    }
+
+    let count = match uint::from_str(words[1]) {
+        Some(n) if n > 0 => n,
+        _ => return Err(fmt!("bad count: %s", words[1]))
+    };
+    let result = match find_item(registry, words[2]) {
+        Ok(item) => ItemStack { item: item, count: count },
+        Err(e) => return Err(e)
+    };
+
+    let pattern = if words[0] == ~"shapeless" {
+        let mut items = ~[];
+        for words.slice(4, words.len()).each |id| {
+            match find_item(registry, *id) {
+                Ok(item) => items.push(item),
+                Err(e) => return Err(e)
+            }
+        }
+        Shapeless(items)
+    } else if words[0] == ~"shaped" {
+        match parse_shaped(words.slice(4, words.len()), registry) {
+            Ok(pattern) => pattern,
+            Err(e) => return Err(e)
+        }
+    } else {
+        return Err(fmt!("unknown recipe kind: %s", words[0]))
+    };
+
+    Ok(Recipe { pattern: pattern, result: result })
+}
+
+fn parse_shaped(words: &[~str], registry: &Registry) -> Result<Pattern, ~str> {
+    let rows: ~[~str] = str::split_char(words[0], '/');
+    let width = rows[0].len();
+    if rows.any(|r| r.len() != width) {
+        return Err(fmt!("rows of %s differ in length", words[0]))
+    }
+
+    let mut keys = ~[];
+    if words.len() > 1 {
+        if words[1] != ~"with" { return Err(fmt!("expected 'with', got %s", words[1])) }
+        for words.slice(2, words.len()).each |key| {
+            if str::find_char(*key, '=') != Some(1) {
+                return Err(fmt!("expected '<letter>=<item>', got %s", *key))
+            }
+            match find_item(registry, key.slice(2, key.len())) {
+                Ok(item) => keys.push((key.char_at(0), item)),
+                Err(e) => return Err(e)
+            }
+        }
+    }
+
+    let mut cells = ~[];
+    for rows.each |row| {
+        for row.each_char |ch| {
+            if ch == '.' {
+                cells.push(None);
+                loop
+            }
+            match keys.find(|&(k, _)| k == ch) {
+                Some((_, item)) => cells.push(Some(item)),
+                None => return Err(fmt!("no item given for '%c'", ch))
+            }
+        }
+    }
+
+    Ok(Shaped(width, rows.len(), cells))
+}
+
+fn find_item(registry: &Registry, id: &str) -> Result<Item, ~str> {
+    match registry.find(id) {
+        Some(item) => Ok(item),
+        None => Err(fmt!("unknown item: %s", id))
+    }
+}
+
+// Reads the recipe file, printing what went wrong and carrying on without recipes if it fails
+pub fn load_recipes(path: &path::Path, registry: &Registry) -> ~[Recipe] {
+    let text = match io::read_whole_file_str(path) {
+        Ok(text) => text,
+        Err(e) => {
+            println(fmt!("cannot read recipes: %s", e));
+            return ~[]
+        }
+    };
+    match parse_recipes(text, registry) {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            println(fmt!("%s: %s", path.to_str(), e));
+            ~[]
+        }
+    }
+}
+
+pub impl Recipe {
+    // whether the items in a size x size grid, row by row, make up this recipe
+    fn matches(&self, grid: &[Option<Item>], size: uint) -> bool {
+        match self.pattern {
+            Shapeless(ref ingredients) => {
+                let mut left = copy *ingredients;
+                for grid.each |cell| {
+                    match *cell {
+                        Some(item) => match left.position_elem(&item) {
+                            Some(i) => { left.swap_remove(i); },
+                            None => return false
+                        },
+                        None => ()
+                    }
+                }
+                left.is_empty()
+            },
+            Shaped(width, height, ref cells) => {
+                // compare against the smallest rectangle holding all of the grid's items
+                let (mut x0, mut y0, mut x1, mut y1) = (size, size, 0, 0);
+                for uint::range(0, size) |y| {
+                    for uint::range(0, size) |x| {
+                        if grid[y * size + x].is_some() {
+                            x0 = uint::min(x0, x); y0 = uint::min(y0, y);
+                            x1 = uint::max(x1, x + 1); y1 = uint::max(y1, y + 1);
+                        }
+                    }
+                }
+                if x1 == 0 || x1 - x0 != width || y1 - y0 != height { return false }
+
+                for uint::range(0, height) |y| {
+                    for uint::range(0, width) |x| {
+                        if grid[(y0 + y) * size + x0 + x] != cells[y * width + x] {
+                            return false
+                        }
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+// A square of item stacks to craft with, independent of any screen
+pub struct CraftingGrid {
+    size: uint,
+    cells: ~[Option<ItemStack>]
+}
+
+pub impl CraftingGrid {
+    fn new(size: uint) -> CraftingGrid {
+        CraftingGrid { size: size, cells: vec::from_elem(size * size, None) }
+    }
+
+    fn items(&self) -> ~[Option<Item>] {
+        self.cells.map(|cell| match *cell {
+            Some(ref stack) => Some(stack.item),
+            None => None
+        })
+    }
+
+    // what crafting would produce right now
+    fn result(&self, recipes: &[Recipe]) -> Option<ItemStack> {
+        let items = self.items();
+        match recipes.position(|r| r.matches(items, self.size)) {
+            Some(i) => Some(recipes[i].result),
+            None => None
+        }
+    }
+
+    // crafts once, using up one item from every cell
+    fn craft(&mut self, recipes: &[Recipe]) -> Option<ItemStack> {
+        let result = self.result(recipes);
+        if result.is_some() {
+            for self.cells.each_mut |cell| {
+                let empty = match *cell {
+                    Some(ref mut stack) => {
+                        stack.count -= 1;
+                        stack.count == 0
+                    },
+                    None => false
+                };
+                if empty { *cell = None; }
+            }
+        }
+        result
+    }
+
+    // adds one item to the cell, returning false if there's something else in it
+    fn put(&mut self, cell: uint, item: Item) -> bool {
+        match self.cells[cell] {
+            None => {
+                self.cells[cell] = Some(ItemStack { item: item, count: 1 });
+                true
+            },
+            Some(ref mut stack) if stack.item == item && stack.count < item.max_stack() => {
+                stack.count += 1;
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn take(&mut self, cell: uint) -> Option<ItemStack> {
+        util::replace(&mut self.cells[cell], None)
+    }
+
+    // takes everything out of the grid
+    fn clear(&mut self) -> ~[ItemStack] {
+        let mut stacks = ~[];
+        for uint::range(0, self.cells.len()) |i| {
+            match self.take(i) {
+                Some(stack) => stacks.push(stack),
+                None => ()
+            }
+        }
+        stacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chunk;
+    use crafting::*;
+    use item::{Item, BlockItem, Stick, ItemStack};
+    use registry::Registry;
+
+    static P: Option<Item> = Some(BlockItem(chunk::Planks));
+    static W: Option<Item> = Some(BlockItem(chunk::Wood));
+
+    fn recipes() -> ~[Recipe] {
+        let text = "shapeless 4 planks <- wood
+                    shaped 4 stick <- p/p with p=planks
+                    shaped 1 door <- pp/pp/pp with p=planks";
+        match parse_recipes(text, &Registry::new()) {
+            Ok(recipes) => recipes,
+            Err(e) => fail!(e)
+        }
+    }
+
+    #[test]
+    fn shapeless_matches_anywhere() {
+        let recipes = recipes();
+        let planks = &recipes[0];
+        assert!(planks.matches([None, None, None, None, None, None, None, None, W], 3));
+        assert!(planks.matches([None, W, None, None], 2));
+        assert!(!planks.matches([W, W, None, None], 2));
+        assert!(!planks.matches([W, P, None, None], 2));
+        assert!(!planks.matches([None, None, None, None], 2));
+    }
+
+    #[test]
+    fn shaped_matches_when_moved_but_not_when_reshaped() {
+        let recipes = recipes();
+        let stick = &recipes[1];
+        assert!(stick.matches([P, None, None, P, None, None, None, None, None], 3));
+        assert!(stick.matches([None, None, None, None, None, P, None, None, P], 3));
+        // side by side instead of on top of each other
+        assert!(!stick.matches([P, P, None, None, None, None, None, None, None], 3));
+        // a gap between them
+        assert!(!stick.matches([P, None, None, None, None, None, P, None, None], 3));
+        assert!(!stick.matches([P, None, None, P, None, None, P, None, None], 3));
+        assert!(!stick.matches([P, None, None, W, None, None, None, None, None], 3));
+
+        let door = &recipes[2];
+        assert!(door.matches([P, P, None, P, P, None, P, P, None], 3));
+        assert!(!door.matches([P, P, None, P, P, None, P, None, None], 3));
+    }
+
+    #[test]
+    fn crafting_uses_one_of_each_ingredient() {
+        let recipes = recipes();
+        let mut grid = CraftingGrid::new(3);
+        assert!(grid.put(1, BlockItem(chunk::Planks)));
+        assert!(grid.put(1, BlockItem(chunk::Planks)));
+        assert!(grid.put(4, BlockItem(chunk::Planks)));
+        assert!(!grid.put(4, BlockItem(chunk::Wood)));
+
+        match grid.craft(recipes) {
+            Some(ItemStack { item: Stick, count: 4 }) => (),
+            result => fail!(fmt!("crafted %?", result))
+        }
+        // the second planks in the top cell is left over and makes nothing on its own
+        assert!(grid.cells[4].is_none());
+        match grid.cells[1] {
+            Some(ItemStack { item: BlockItem(chunk::Planks), count: 1 }) => (),
+            cell => fail!(fmt!("left %?", cell))
+        }
+        assert!(grid.craft(recipes).is_none());
+        assert!(grid.cells[1].is_some());
+    }
+}
@@ -0,0 +1,72 @@
+use common::*;
+use core::float;
+use numeric::*;
+
+// ticks in a whole day and night; the world starts at sunrise
+pub static DAY_LENGTH: u64 = 24000;
+
+static DAY_SKY: (float, float, float) = (0.53, 0.81, 0.98);
+static DUSK_SKY: (float, float, float) = (0.85, 0.5, 0.35);
+static NIGHT_SKY: (float, float, float) = (0.02, 0.02, 0.07);
+static SUN_COLOUR: (float, float, float) = (1.0, 1.0, 0.95);
+static DUSK_SUN_COLOUR: (float, float, float) = (1.0, 0.6, 0.35);
+static MOON_COLOUR: (float, float, float) = (0.2, 0.22, 0.35);
+// the sun's height over the horizon, as the sine of its angle, where dusk ends
+static TWILIGHT: float = 0.2;
+// fraction of full sky light left on a moonlit night
+static NIGHT_LIGHT: float = 0.25;
+
+fn colour(c: (float, float, float)) -> Vec3f {
+    let (r, g, b) = c;
+    Vec3f::new(r, g, b)
+}
+
+fn clamp(x: float) -> float {
+    if x < 0.0 { 0.0 } else if x > 1.0 { 1.0 } else { x }
+}
+
+// the sun's angle around the world, 0 at sunrise and pi at sunset
+pub fn sun_angle(time: u64) -> float {
+    (time % DAY_LENGTH) as float / DAY_LENGTH as float * 2.0 * float::consts::pi
+}
+
+// unit vector pointing towards the sun; below the horizon at night
+pub fn sun_direction(time: u64) -> Vec3f {
+    let angle = sun_angle(time);
+    // tilted a little, so that faces lit from the east and west aren't the same
+    let v = Vec3f::new(float::cos(angle), float::sin(angle), 0.3);
+    v.div_t(v.length())
+}
+
+// how far into daylight it is: 0 through the night, 1 once the sun is up past dusk
+pub fn daylight(time: u64) -> float {
+    clamp((sun_direction(time).y + TWILIGHT) / (2.0 * TWILIGHT))
+}
+
+/* The direction light comes from, which is the moon opposite the sun at night, and its
+ * colour.
+ */
+pub fn light(time: u64) -> (Vec3f, Vec3f) {
+    let sun = sun_direction(time);
+    let dusk = colour(DUSK_SUN_COLOUR);
+    if sun.y >= 0.0 {
+        (sun, mix(&dusk, &colour(SUN_COLOUR), clamp(sun.y / TWILIGHT)))
+    } else {
+        (sun.mul_t(-1.0), mix(&dusk, &colour(MOON_COLOUR), clamp(-sun.y / TWILIGHT)))
+    }
+}
+
+// blends from night through dusk to day as the sun rises
+pub fn sky_colour(time: u64) -> Vec3f {
+    let h = sun_direction(time).y;
+    if h >= 0.0 {
+        mix(&colour(DUSK_SKY), &colour(DAY_SKY), clamp(h / TWILIGHT))
+    } else {
+        mix(&colour(DUSK_SKY), &colour(NIGHT_SKY), clamp(-h / TWILIGHT))
+    }
+}
+
+// how much of the full sky light reaches open ground
+pub fn sky_light_factor(time: u64) -> float {
+    NIGHT_LIGHT + (1.0 - NIGHT_LIGHT) * daylight(time)
+}
@@ -0,0 +1,220 @@
+use chunk;
+use chunk::BlockMesh;
+use shader::Program;
+use world;
+use world::World;
+use aabb;
+use aabb::Aabb;
+use item::{Item, ItemStack};
+use mob;
+use player::Player;
+
+use common::*;
+use coords::BlockPos;
+use core::float;
+
+// item entities are removed after this many ticks
+pub static ITEM_LIFETIME: uint = 6000;
+// ticks before a dropped item can be picked up
+pub static PICKUP_DELAY: uint = 10;
+static ITEM_SIZE: float = 0.25;
+// fraction of horizontal velocity kept per tick on the ground
+static GROUND_FRICTION: float = 0.6;
+
+pub enum EntityKind {
+    // a gravity-affected block on its way down
+    FallingBlock(chunk::Block),
+    // items lying in the world waiting to be picked up
+    ItemDrop(ItemStack),
+    Mob(mob::Mob)
+}
+
+// What the world should do with an entity after ticking it
+pub enum EntityAction {
+    Keep,
+    Remove,
+    // remove the entity and put the block in its place, or drop it as an item if it can't be
+    PlaceBlock(BlockPos, chunk::Block),
+    // keep the entity and hurt the player by this much
+    Attack(int)
+}
+
+/* Anything that moves around the world apart from the player. Entities are kept in the
+ * chunk they're in, and saved and unloaded along with it.
+ */
+pub struct Entity {
+    // bottom centre of the entity
+    position: Vec3f,
+    velocity: Vec3f,
+    on_ground: bool,
+    // ticks since the entity was spawned
+    age: uint,
+    kind: EntityKind
+}
+
+pub impl EntityKind {
+    // width and height of the entity's box
+    fn size(&self) -> (float, float) {
+        match *self {
+            FallingBlock(_) => (0.98, 0.98),
+            ItemDrop(_) => (ITEM_SIZE, ITEM_SIZE),
+            Mob(ref mob) => mob.kind.size()
+        }
+    }
+}
+
+pub impl Entity {
+    fn new(kind: EntityKind, position: Vec3f) -> Entity {
+        Entity {
+            position: position,
+            velocity: Vec3f::zero(),
+            on_ground: false,
+            age: 0,
+            kind: kind
+        }
+    }
+
+    fn falling_block(block: chunk::Block, pos: BlockPos) -> Entity {
+        Entity::new(FallingBlock(block),
+                    Vec3f::new(pos.x as float + 0.5, pos.y as float, pos.z as float + 0.5))
+    }
+
+    fn item(stack: ItemStack, position: Vec3f) -> Entity {
+        let mut e = Entity::new(ItemDrop(stack), position);
+        // pop up a little when dropped
+        e.velocity.y = 4.0;
+        e
+    }
+
+    fn aabb(&self) -> Aabb {
+        let (width, height) = self.kind.size();
+        Aabb::from_feet(&self.position, width, height)
+    }
+
+    // falls and moves with the entity's velocity, stopping at blocks
+    fn physics(&mut self, world: &World, dt: float) {
+        self.velocity.y -= world::GRAVITY * dt;
+        let (moved, (hit_x, hit_y, hit_z)) =
+            aabb::move_box(world, &self.aabb(), &self.velocity.mul_t(dt));
+        self.position.add_self_v(&moved);
+
+        self.on_ground = hit_y && self.velocity.y < 0.0;
+        if hit_x { self.velocity.x = 0.0; }
+        if hit_y { self.velocity.y = 0.0; }
+        if hit_z { self.velocity.z = 0.0; }
+        if self.on_ground {
+            self.velocity.x *= GROUND_FRICTION;
+            self.velocity.z *= GROUND_FRICTION;
+        }
+    }
+
+    fn tick(&mut self, world: &World, player: &Player, dt: float) -> EntityAction {
+        self.age += 1;
+
+        match self.kind {
+            FallingBlock(block) => {
+                self.physics(world, dt);
+                if !self.on_ground { return Keep }
+
+                let pos = BlockPos::from_vec(&self.position.add_v(&Vec3f::new(0.0, 0.5, 0.0)));
+                PlaceBlock(pos, block)
+            },
+            ItemDrop(_) => {
+                if self.age > ITEM_LIFETIME { return Remove }
+                self.physics(world, dt);
+                Keep
+            },
+            Mob(_) => self.tick_mob(world, player, dt)
+        }
+    }
+
+    fn tick_mob(&mut self, world: &World, player: &Player, dt: float) -> EntityAction {
+        let (position, on_ground) = (self.position, self.on_ground);
+        let action = match self.kind {
+            Mob(ref mut mob) => mob.tick(world, player, &position, on_ground, &mut self.velocity),
+            _ => Keep
+        };
+        self.physics(world, dt);
+        action
+    }
+
+    fn draw(&self, program: &mut Program, mesh: &BlockMesh, camera_matrix: &Mat4f) {
+        match self.kind {
+            FallingBlock(block) => {
+                let modelview = camera_matrix.mul_m(&translation_matrix(
+                    (self.position.x - 0.5, self.position.y, self.position.z - 0.5)));
+                program.set_uniform_mat4("modelview", &modelview);
+                mesh.draw_block(program, block);
+            },
+            ItemDrop(ref stack) => {
+                // a small block bobbing above the ground and slowly turning
+                let bob = 0.1 + 0.1 * float::sin(self.age as float * 0.1);
+                let rotation = Quatf::from_angle_axis(self.age as float * 0.05,
+                                                      &Vec3f::new(0.0, 1.0, 0.0));
+                let modelview = camera_matrix.mul_m(&translation_matrix(
+                    (self.position.x, self.position.y + ITEM_SIZE / 2.0 + bob, self.position.z)))
+                    .mul_m(&rotation.to_mat3().to_mat4()).mul_m(&scale_matrix(ITEM_SIZE))
+                    .mul_m(&translation_matrix((-0.5, -0.5, -0.5)));
+                program.set_uniform_mat4("modelview", &modelview);
+                mesh.draw_block(program, stack.item.icon_block());
+            },
+            Mob(ref mob) => mob.draw(program, mesh, camera_matrix, &self.position)
+        }
+    }
+
+    fn write(&self, w: @io::Writer) {
+        match self.kind {
+            FallingBlock(block) => {
+                let (id, data) = block.to_id();
+                w.write_u8(0);
+                w.write_u8(id);
+                w.write_u8(data);
+            },
+            ItemDrop(ref stack) => {
+                w.write_u8(1);
+                stack.item.write(w);
+                w.write_be_u32(stack.count as u32);
+            },
+            Mob(ref mob) => {
+                w.write_u8(2);
+                w.write_u8(mob.kind.to_id());
+            }
+        }
+        for [self.position, self.velocity].each |v| {
+            w.write_be_f64(v.x as f64);
+            w.write_be_f64(v.y as f64);
+            w.write_be_f64(v.z as f64);
+        }
+        w.write_be_u32(self.age as u32);
+    }
+
+    fn read(r: @io::Reader) -> Option<Entity> {
+        let kind = match r.read_u8() {
+            0 => {
+                let (id, data) = (r.read_u8(), r.read_u8());
+                match chunk::Block::from_id(id, data) {
+                    Some(b) => FallingBlock(b),
+                    None => return None
+                }
+            },
+            1 => match Item::read(r) {
+                Some(item) => ItemDrop(ItemStack { item: item, count: r.read_be_u32() as uint }),
+                None => return None
+            },
+            2 => match mob::MobKind::from_id(r.read_u8()) {
+                Some(kind) => Mob(mob::Mob::new(kind)),
+                None => return None
+            },
+            _ => return None
+        };
+
+        let read_vec = || {
+            let (x, y, z) = (r.read_be_f64(), r.read_be_f64(), r.read_be_f64());
+            Vec3f::new(x as float, y as float, z as float)
+        };
+        let mut e = Entity::new(kind, read_vec());
+        e.velocity = read_vec();
+        e.age = r.read_be_u32() as uint;
+        Some(e)
+    }
+}
@@ -0,0 +1,148 @@
+use chunk;
+use chunk::{Block, Water, Lava};
+use world::{World, Unloaded};
+use coords::BlockPos;
+
+// ticks between updates of a fluid block; lava is a lot more sluggish than water
+static WATER_DELAY: u64 = 5;
+static LAVA_DELAY: u64 = 30;
+
+pub fn delay(b: Block) -> u64 {
+    match b {
+        Lava(_) => LAVA_DELAY,
+        _ => WATER_DELAY
+    }
+}
+
+fn level(b: Block) -> u8 {
+    match b {
+        Water(l) | Lava(l) => l,
+        _ => 0
+    }
+}
+
+fn with_level(b: Block, level: u8) -> Block {
+    match b {
+        Water(_) => Water(level),
+        Lava(_) => Lava(level),
+        b => b
+    }
+}
+
+pub fn same_kind(a: Block, b: Block) -> bool {
+    match (a, b) {
+        (Water(_), Water(_)) | (Lava(_), Lava(_)) => true,
+        _ => false
+    }
+}
+
+// how many blocks a fluid flows sideways from a source on flat ground
+fn max_level(b: Block) -> u8 {
+    match b {
+        Lava(_) => 3,
+        _ => 7
+    }
+}
+
+// height of the fluid's surface in its block, lower the further it has flowed
+pub fn surface_height(b: Block) -> float {
+    1.0 - (level(b) + 1) as float / 9.0
+}
+
+/* Changes the block at, on behalf of the fluid at pos. An edit into a chunk that isn't
+ * loaded is retried on the fluid's next update; protected blocks just stay as they are.
+ */
+fn set(world: &mut World, pos: BlockPos, at: BlockPos, b: Block) {
+    match world.replace_block(at, b) {
+        Err(Unloaded) => {
+            let retry = match world.block(pos) {
+                Some(here) => delay(here),
+                None => return
+            };
+            world.schedule_tick(pos, retry);
+        },
+        _ => ()
+    }
+}
+
+/* Runs one scheduled update of the fluid at pos. Every change goes through
+ * World::replace_block, whose neighbour updates schedule the surrounding fluid in turn.
+ */
+pub fn update(world: &mut World, pos: BlockPos) {
+    let here = match world.block(pos) {
+        Some(b) if b.is_fluid() => b,
+        _ => return
+    };
+
+    // lava and water meeting turns the lava into stone
+    for pos.each_neighbour |n| {
+        match (here, world.block(n)) {
+            (Lava(_), Some(Water(_))) => {
+                set(world, pos, pos, chunk::Stone);
+                return
+            },
+            (Water(_), Some(Lava(_))) => set(world, pos, n, chunk::Stone),
+            _ => ()
+        }
+    }
+
+    let below = pos.down();
+    let lvl = level(here);
+
+    if lvl > 0 {
+        // flowing blocks follow whatever is feeding them, or dry up
+        let mut new_level = 255u8;
+        let mut sources = 0;
+        match world.block(pos.up()) {
+            Some(b) if same_kind(b, here) => new_level = 1,
+            _ => {
+                for pos.each_horizontal_neighbour |n| {
+                    match world.block(n) {
+                        Some(b) if same_kind(b, here) => {
+                            if level(b) == 0 { sources += 1 }
+                            if level(b) + 1 < new_level { new_level = level(b) + 1 }
+                        },
+                        _ => ()
+                    }
+                }
+            }
+        }
+
+        // two water sources side by side fill the gap with a new one
+        match (here, world.block(below)) {
+            (Water(_), Some(b)) if sources >= 2 && (b.blocks() || b == Water(0)) => new_level = 0,
+            _ => ()
+        }
+
+        if new_level > max_level(here) {
+            set(world, pos, pos, chunk::Air);
+            return
+        }
+        if new_level != lvl {
+            set(world, pos, pos, with_level(here, new_level));
+            return
+        }
+    }
+
+    // falling takes priority over spreading
+    match world.block(below) {
+        Some(chunk::Air) => {
+            set(world, pos, below, with_level(here, 1));
+            return
+        },
+        Some(b) if same_kind(b, here) && level(b) > 0 => return,
+        _ => ()
+    }
+
+    if lvl >= max_level(here) { return }
+
+    for pos.each_horizontal_neighbour |side| {
+        match world.block(side) {
+            Some(chunk::Air) => set(world, pos, side, with_level(here, lvl + 1)),
+            Some(b) if same_kind(b, here) && level(b) > lvl + 1 => {
+                set(world, pos, side, with_level(here, lvl + 1));
+            },
+            _ => ()
+        }
+    }
+}
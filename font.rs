@@ -25,6 +25,11 @@ pub impl Font {
     }
 
     fn draw(&self, message: &str) {
+        self.draw_at(message, 0.0, 0.0);
+    }
+
+    // draws the message with its lower left corner at (x, y), the screen being 33.3x20 units
+    fn draw_at(&self, message: &str, x: float, y: float) {
         let mut vbuf = Buffer::new();
         let mut tbuf = Buffer::new();
 
@@ -35,7 +40,7 @@ pub impl Font {
         for message.each_chari |index, ch| {
             match str::find_char(self.map, ch) {
                 Some(map_index) => {
-                    vs.push_all_move(make_cube(index as float * 0.6, 0.0));
+                    vs.push_all_move(make_cube(x + index as float * 0.6, y));
                     ts.push_all_move(make_cube_texcoord(map_index));
                     drawn_chars += 1;
                 },
@@ -0,0 +1,176 @@
+use chunk::BlockMesh;
+use font::Font;
+use item;
+use item::{Item, Inventory};
+use crafting::{CraftingGrid, Recipe};
+use player;
+use hunger;
+use player::Player;
+use shader::Program;
+use texture::Texture;
+
+use glcore::*;
+use common::*;
+
+use lmath;
+
+// the HUD is laid out in the same 33.3x20 units as Font
+static HUD_WIDTH: float = 33.3;
+static HUD_HEIGHT: float = 20.0;
+static SLOT_SIZE: float = 1.5;
+
+fn hud_projection() -> Mat4f {
+    lmath::projection::ortho(0.0, HUD_WIDTH, 0.0, HUD_HEIGHT, -1.0, 1.0)
+}
+
+// draws the item's icon centred on (x, y)
+fn draw_item(program: &mut Program, mesh: &BlockMesh, projection: &Mat4f, item: Item,
+             x: float, y: float)
+{
+    // tilted so that the top and two sides of each block are visible
+    let rotation = Quatf::from_angle_axis(0.5, &Vec3f::new(1.0, 0.0, 0.0)).mul_q(
+        &Quatf::from_angle_axis(0.785, &Vec3f::new(0.0, 1.0, 0.0))).to_mat3().to_mat4();
+    let modelview = translation_matrix((x, y, 0.0))
+        .mul_m(&rotation).mul_m(&scale_matrix(0.7))
+        .mul_m(&translation_matrix((-0.5, -0.5, -0.5)));
+    program.bind();
+    program.set_uniform_mat4("projection", projection);
+    program.set_uniform_mat4("modelview", &modelview);
+    mesh.draw_block(program, item.icon_block());
+}
+
+// Draws the hotbar along the bottom of the screen, each slot showing its block and count
+pub fn draw_hotbar(program: &mut Program, mesh: &BlockMesh, blocks: &Texture, font: &Font,
+                   inventory: &Inventory)
+{
+    let left = (HUD_WIDTH - SLOT_SIZE * item::HOTBAR_SIZE as float) / 2.0;
+
+    let projection = hud_projection();
+
+    glDisable(GL_DEPTH_TEST);
+
+    // items first, as the font binds its own texture
+    blocks.bind(0);
+    for uint::range(0, item::HOTBAR_SIZE) |i| {
+        match inventory.slots[i] {
+            Some(ref stack) => draw_item(program, mesh, &projection, stack.item,
+                                         left + i as float * SLOT_SIZE + SLOT_SIZE / 2.0, 1.6),
+            None => ()
+        }
+    }
+
+    for uint::range(0, item::HOTBAR_SIZE) |i| {
+        let x = left + i as float * SLOT_SIZE;
+        match inventory.slots[i] {
+            Some(ref stack) if stack.count > 1 => {
+                font.draw_at(fmt!("%u", stack.count), x + 0.2, 0.2);
+            },
+            Some(ref stack) => match stack.item.durability_left() {
+                Some(left) => font.draw_at(fmt!("%d%%", (left * 100.0) as int), x + 0.2, 0.2),
+                None => ()
+            },
+            None => ()
+        }
+
+        if i == inventory.selected {
+            font.draw_at("[", x - 0.1, 1.1);
+            font.draw_at("]", x + SLOT_SIZE - 0.4, 1.1);
+        }
+    }
+
+    glEnable(GL_DEPTH_TEST);
+}
+
+/* Shows the game mode, or health, food and air in survival, just above the hotbar, and for a
+ * while after respawning that the player died
+ */
+pub fn draw_status(font: &Font, player: &Player) {
+    if player.death_message > 0 {
+        font.draw_at("YOU DIED", HUD_WIDTH / 2.0 - 2.0, HUD_HEIGHT / 2.0 + 2.0);
+    }
+    if player.is_creative() {
+        font.draw_at("CREATIVE", 1.0, 3.0);
+        return
+    }
+
+    font.draw_at(fmt!("HP %d/%d", player.health, player::MAX_HEALTH), 1.0, 4.0);
+    font.draw_at(fmt!("FOOD %d/%d", player.hunger.food, hunger::MAX_FOOD), 1.0, 3.0);
+    if player.air < player::MAX_AIR {
+        // in seconds, rounded up
+        let seconds = (int::max(player.air, 0) + 19) / 20;
+        font.draw_at(fmt!("AIR %d", seconds), 1.0, 2.0);
+    }
+}
+
+// The crafting grid screen; the cursor is the cell items are put into and taken from
+pub struct CraftingScreen {
+    grid: CraftingGrid,
+    cursor: uint
+}
+
+pub impl CraftingScreen {
+    fn new() -> CraftingScreen {
+        CraftingScreen { grid: CraftingGrid::new(3), cursor: 0 }
+    }
+
+    fn move_cursor(&mut self, dx: int, dy: int) {
+        let size = self.grid.size as int;
+        let x = (self.cursor as int % size + dx + size) % size;
+        let y = (self.cursor as int / size + dy + size) % size;
+        self.cursor = (y * size + x) as uint;
+    }
+}
+
+/* Draws the crafting grid in the middle of the screen with the result of crafting next to
+ * it, along with a reminder of the keys
+ */
+pub fn draw_crafting(program: &mut Program, mesh: &BlockMesh, blocks: &Texture, font: &Font,
+                     screen: &CraftingScreen, recipes: &[Recipe])
+{
+    let size = screen.grid.size;
+    let left = HUD_WIDTH / 2.0 - SLOT_SIZE * size as float;
+    let top = 14.0;
+    let cell_x = |i: uint| left + (i % size) as float * SLOT_SIZE;
+    let cell_y = |i: uint| top - (i / size) as float * SLOT_SIZE;
+    let result_x = left + (size + 1) as float * SLOT_SIZE;
+    let result = screen.grid.result(recipes);
+
+    let projection = hud_projection();
+    glDisable(GL_DEPTH_TEST);
+
+    blocks.bind(0);
+    for screen.grid.cells.eachi |i, cell| {
+        match *cell {
+            Some(ref stack) => draw_item(program, mesh, &projection, stack.item,
+                                         cell_x(i) + SLOT_SIZE / 2.0, cell_y(i) + 0.5),
+            None => ()
+        }
+    }
+    match result {
+        Some(ref stack) => draw_item(program, mesh, &projection, stack.item,
+                                     result_x + SLOT_SIZE / 2.0, cell_y(size) + 0.5),
+        None => ()
+    }
+
+    for screen.grid.cells.eachi |i, cell| {
+        match *cell {
+            Some(ref stack) if stack.count > 1 =>
+                font.draw_at(fmt!("%u", stack.count), cell_x(i) + 0.2, cell_y(i) - 0.9),
+            Some(_) => (),
+            None => font.draw_at(".", cell_x(i) + 0.5, cell_y(i))
+        }
+    }
+    font.draw_at("[", cell_x(screen.cursor) - 0.1, cell_y(screen.cursor));
+    font.draw_at("]", cell_x(screen.cursor) + SLOT_SIZE - 0.4, cell_y(screen.cursor));
+
+    font.draw_at("->", result_x - SLOT_SIZE + 0.3, cell_y(size));
+    match result {
+        Some(ref stack) => font.draw_at(fmt!("%u %s", stack.count, stack.item.name()),
+                                        result_x, cell_y(size) - 1.2),
+        None => ()
+    }
+
+    font.draw_at("Q put   BACKSPACE take   ENTER craft   E close", 2.0, top + 2.0);
+
+    glEnable(GL_DEPTH_TEST);
+}
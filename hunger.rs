@@ -0,0 +1,106 @@
+use config::Config;
+
+pub static MAX_FOOD: int = 20;
+
+// How fast hunger drains and what it does, loaded from the config file
+pub struct HungerConfig {
+    // exhaustion that costs one food point
+    exhaustion_per_food: float,
+    // per block moved while sprinting
+    sprint_exhaustion: float,
+    jump_exhaustion: float,
+    // per block broken
+    mining_exhaustion: float,
+    // health regenerates while food is at least this
+    regen_food: int,
+    // sprinting is only possible above this much food
+    sprint_food: int,
+    // ticks between healing one point, or taking one point of damage when starving
+    interval: uint,
+    regen_exhaustion: float
+}
+
+pub impl HungerConfig {
+    fn from_config(config: &Config) -> HungerConfig {
+        HungerConfig {
+            exhaustion_per_food: config.get_float("exhaustion_per_food", 4.0),
+            sprint_exhaustion: config.get_float("sprint_exhaustion", 0.1),
+            jump_exhaustion: config.get_float("jump_exhaustion", 0.05),
+            mining_exhaustion: config.get_float("mining_exhaustion", 0.025),
+            regen_food: get_non_negative(config, "regen_food", 18),
+            sprint_food: get_non_negative(config, "sprint_food", 6),
+            interval: get_non_negative(config, "hunger_interval", 80) as uint,
+            regen_exhaustion: config.get_float("regen_exhaustion", 3.0)
+        }
+    }
+}
+
+// a whole number setting that makes no sense below zero, where it's clamped to zero
+fn get_non_negative(config: &Config, key: &str, default: int) -> int {
+    let value = config.get_int(key, default);
+    if value < 0 {
+        println(fmt!("config: %s can't be negative, using 0: %d", key, value));
+        return 0
+    }
+    value
+}
+
+pub struct Hunger {
+    config: HungerConfig,
+    food: int,
+    // builds up with activity until it uses up a food point
+    exhaustion: float,
+    // ticks since health last changed because of hunger
+    timer: uint
+}
+
+pub impl Hunger {
+    fn new(config: HungerConfig) -> Hunger {
+        Hunger { config: config, food: MAX_FOOD, exhaustion: 0.0, timer: 0 }
+    }
+
+    fn exhaust(&mut self, amount: float) {
+        self.exhaustion += amount;
+        while self.exhaustion >= self.config.exhaustion_per_food {
+            self.exhaustion -= self.config.exhaustion_per_food;
+            self.food = int::max(self.food - 1, 0);
+        }
+    }
+
+    fn eat(&mut self, food: int) {
+        self.food = int::min(self.food + food, MAX_FOOD);
+    }
+
+    fn can_sprint(&self) -> bool {
+        self.food > self.config.sprint_food
+    }
+
+    fn reset(&mut self) {
+        self.food = MAX_FOOD;
+        self.exhaustion = 0.0;
+        self.timer = 0;
+    }
+
+    /* Advances by one tick, returning the change in health: healing while well fed, damage
+     * while starving
+     */
+    fn tick(&mut self, health: int, max_health: int) -> int {
+        let regen = self.food >= self.config.regen_food && health < max_health;
+        let starving = self.food == 0;
+        if !regen && !starving {
+            self.timer = 0;
+            return 0
+        }
+
+        self.timer += 1;
+        if self.timer < self.config.interval { return 0 }
+        self.timer = 0;
+
+        if regen {
+            self.exhaust(self.config.regen_exhaustion);
+            1
+        } else {
+            -1
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use chunk;
+
+use core::util;
+
+pub static INVENTORY_SIZE: uint = 36;
+// the first slots of the inventory make up the hotbar
+pub static HOTBAR_SIZE: uint = 9;
+static MAX_STACK: uint = 64;
+
+// the kinds of blocks a tool is good for
+#[deriving(Eq)]
+pub enum ToolClass {
+    Pickaxe,
+    Shovel,
+    Axe
+}
+
+#[deriving(Eq)]
+pub enum ToolTier {
+    WoodenTier,
+    StoneTier,
+    IronTier,
+    DiamondTier
+}
+
+pub impl ToolTier {
+    fn level(&self) -> uint {
+        match *self {
+            WoodenTier => 0,
+            StoneTier => 1,
+            IronTier => 2,
+            DiamondTier => 3
+        }
+    }
+
+    // how many times faster than by hand blocks of the tool's class are broken
+    fn speed(&self) -> float {
+        match *self {
+            WoodenTier => 2.0,
+            StoneTier => 4.0,
+            IronTier => 6.0,
+            DiamondTier => 8.0
+        }
+    }
+
+    // number of blocks a tool can break before it's used up
+    fn durability(&self) -> uint {
+        match *self {
+            WoodenTier => 60,
+            StoneTier => 132,
+            IronTier => 251,
+            DiamondTier => 1562
+        }
+    }
+}
+
+#[deriving(Eq)]
+pub enum Item {
+    // a block that can be placed back into the world
+    BlockItem(chunk::Block),
+    Stick,
+    // dropped by ripe wheat
+    Grain,
+    Bread,
+    // the last field is the wear, the number of times the tool has been used
+    Tool(ToolClass, ToolTier, uint)
+}
+
+// items handed out when switching to creative mode
+pub static creative_palette: [Item, ..9] = [
+    BlockItem(chunk::Stone), BlockItem(chunk::Cobblestone), BlockItem(chunk::Dirt),
+    BlockItem(chunk::Planks), Bread, BlockItem(chunk::Slab),
+    BlockItem(chunk::Sand), BlockItem(chunk::Torch), BlockItem(chunk::Water(0))
+];
+
+// items that aren't blocks, for the registry
+pub static plain_items: [Item, ..15] = [
+    Stick, Grain, Bread,
+    Tool(Pickaxe, WoodenTier, 0), Tool(Pickaxe, StoneTier, 0),
+    Tool(Pickaxe, IronTier, 0), Tool(Pickaxe, DiamondTier, 0),
+    Tool(Shovel, WoodenTier, 0), Tool(Shovel, StoneTier, 0),
+    Tool(Shovel, IronTier, 0), Tool(Shovel, DiamondTier, 0),
+    Tool(Axe, WoodenTier, 0), Tool(Axe, StoneTier, 0),
+    Tool(Axe, IronTier, 0), Tool(Axe, DiamondTier, 0)
+];
+
+pub impl Item {
+    fn max_stack(&self) -> uint {
+        match *self {
+            Tool(*) => 1,
+            _ => MAX_STACK
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            BlockItem(b) => b.name(),
+            Stick => "stick",
+            Grain => "grain",
+            Bread => "bread",
+            Tool(class, tier, _) => match (class, tier) {
+                (Pickaxe, WoodenTier) => "wooden pickaxe",
+                (Pickaxe, StoneTier) => "stone pickaxe",
+                (Pickaxe, IronTier) => "iron pickaxe",
+                (Pickaxe, DiamondTier) => "diamond pickaxe",
+                (Shovel, WoodenTier) => "wooden shovel",
+                (Shovel, StoneTier) => "stone shovel",
+                (Shovel, IronTier) => "iron shovel",
+                (Shovel, DiamondTier) => "diamond shovel",
+                (Axe, WoodenTier) => "wooden axe",
+                (Axe, StoneTier) => "stone axe",
+                (Axe, IronTier) => "iron axe",
+                (Axe, DiamondTier) => "diamond axe"
+            }
+        }
+    }
+
+    // the name used in data files, e.g. "coal_ore"
+    fn id(&self) -> ~str {
+        str::replace(self.name(), " ", "_")
+    }
+
+    fn as_block(&self) -> Option<chunk::Block> {
+        match *self {
+            BlockItem(b) => Some(b),
+            _ => None
+        }
+    }
+
+    // the block drawn for the item in the HUD and as an item entity
+    fn icon_block(&self) -> chunk::Block {
+        match *self {
+            BlockItem(b) => b,
+            Stick => chunk::Wood,
+            Grain => chunk::Wheat(7),
+            Bread => chunk::Planks,
+            Tool(_, WoodenTier, _) => chunk::Planks,
+            Tool(_, StoneTier, _) => chunk::Cobblestone,
+            Tool(_, IronTier, _) => chunk::IronOre,
+            Tool(_, DiamondTier, _) => chunk::DiamondOre
+        }
+    }
+
+    // food points restored by eating the item, None if it can't be eaten
+    fn food_value(&self) -> Option<int> {
+        match *self {
+            Bread => Some(5),
+            _ => None
+        }
+    }
+
+    fn write(&self, w: @io::Writer) {
+        match *self {
+            BlockItem(b) => {
+                let (id, data) = b.to_id();
+                w.write_u8(0);
+                w.write_u8(id);
+                w.write_u8(data);
+            },
+            Stick => w.write_u8(1),
+            Grain => w.write_u8(2),
+            Bread => w.write_u8(3),
+            Tool(class, tier, wear) => {
+                w.write_u8(4);
+                w.write_u8(class as u8);
+                w.write_u8(tier.level() as u8);
+                w.write_be_u32(wear as u32);
+            }
+        }
+    }
+
+    fn read(r: @io::Reader) -> Option<Item> {
+        match r.read_u8() {
+            0 => {
+                let (id, data) = (r.read_u8(), r.read_u8());
+                match chunk::Block::from_id(id, data) {
+                    Some(b) => Some(BlockItem(b)),
+                    None => None
+                }
+            },
+            1 => Some(Stick),
+            2 => Some(Grain),
+            3 => Some(Bread),
+            4 => {
+                let class = match r.read_u8() {
+                    0 => Pickaxe,
+                    1 => Shovel,
+                    2 => Axe,
+                    _ => return None
+                };
+                let tier = match r.read_u8() {
+                    0 => WoodenTier,
+                    1 => StoneTier,
+                    2 => IronTier,
+                    3 => DiamondTier,
+                    _ => return None
+                };
+                Some(Tool(class, tier, r.read_be_u32() as uint))
+            },
+            _ => None
+        }
+    }
+
+    // fraction of a tool's durability left, None for other items
+    fn durability_left(&self) -> Option<float> {
+        match *self {
+            Tool(_, tier, wear) => Some(1.0 - wear as float / tier.durability() as float),
+            _ => None
+        }
+    }
+}
+
+// Whether breaking the block with the item in hand gives its drops
+pub fn can_harvest(block: chunk::Block, held: Option<Item>) -> bool {
+    match (block.required_tool(), held) {
+        (None, _) => true,
+        (Some((class, tier)), Some(Tool(c, t, _))) => c == class && t.level() >= tier.level(),
+        _ => false
+    }
+}
+
+/* Seconds it takes to break the block with the item in hand. The right tool is faster, and
+ * blocks that need a tool take much longer without one.
+ */
+pub fn mining_time(block: chunk::Block, held: Option<Item>) -> float {
+    let speed = match (block.tool_class(), held) {
+        (Some(class), Some(Tool(c, tier, _))) if c == class => tier.speed(),
+        _ => 1.0
+    };
+    let penalty = if can_harvest(block, held) { 1.5 } else { 5.0 };
+    block.hardness() * penalty / speed
+}
+
+pub struct ItemStack {
+    item: Item,
+    count: uint
+}
+
+// What a block leaves behind when it's broken
+pub fn block_drops(block: chunk::Block) -> ~[ItemStack] {
+    let single = |item: Item| ~[ItemStack { item: item, count: 1 }];
+    match block {
+        chunk::Air | chunk::Bedrock | chunk::Water(_) | chunk::Lava(_) => ~[],
+        chunk::Leaves => ~[],
+        chunk::Stone => single(BlockItem(chunk::Cobblestone)),
+        chunk::Grass => single(BlockItem(chunk::Dirt)),
+        // replanting starts from the first stage
+        chunk::Wheat(7) => ~[ItemStack { item: BlockItem(chunk::Wheat(0)), count: 1 },
+                             ItemStack { item: Grain, count: 1 }],
+        chunk::Wheat(_) => single(BlockItem(chunk::Wheat(0))),
+        chunk::Door(_) => single(BlockItem(chunk::Door(false))),
+        b => single(BlockItem(b))
+    }
+}
+
+pub struct Inventory {
+    slots: ~[Option<ItemStack>],
+    // index of the hotbar slot in hand
+    selected: uint
+}
+
+pub impl Inventory {
+    fn new() -> Inventory {
+        Inventory { slots: vec::from_elem(INVENTORY_SIZE, None), selected: 0 }
+    }
+
+    /* Adds count items, topping up existing stacks before starting new ones. Returns how
+     * many didn't fit.
+     */
+    fn add(&mut self, item: Item, count: uint) -> uint {
+        let mut left = count;
+
+        for self.slots.each_mut |slot| {
+            if left == 0 { break }
+            match *slot {
+                Some(ref mut stack) if stack.item == item => {
+                    let n = uint::min(left, item.max_stack() - stack.count);
+                    stack.count += n;
+                    left -= n;
+                },
+                _ => ()
+            }
+        }
+
+        for self.slots.each_mut |slot| {
+            if left == 0 { break }
+            if slot.is_none() {
+                let n = uint::min(left, item.max_stack());
+                *slot = Some(ItemStack { item: item, count: n });
+                left -= n;
+            }
+        }
+
+        left
+    }
+
+    fn selected_item(&self) -> Option<Item> {
+        match self.slots[self.selected] {
+            Some(ref stack) => Some(stack.item),
+            None => None
+        }
+    }
+
+    // puts each of the items into an empty hotbar slot unless it's already there
+    fn fill_hotbar(&mut self, items: &[Item]) {
+        for items.each |&item| {
+            let (present, empty) = {
+                let hotbar = self.slots.slice(0, HOTBAR_SIZE);
+                (hotbar.any(|s| match *s { Some(ref stack) => stack.item == item, None => false }),
+                 hotbar.position(|s| s.is_none()))
+            };
+            if present { loop }
+            match empty {
+                Some(i) => self.slots[i] = Some(ItemStack { item: item, count: item.max_stack() }),
+                None => return
+            }
+        }
+    }
+
+    // empties the inventory, returning everything that was in it
+    fn take_all(&mut self) -> ~[ItemStack] {
+        let mut stacks = ~[];
+        for self.slots.each_mut |slot| {
+            match util::replace(slot, None) {
+                Some(stack) => stacks.push(stack),
+                None => ()
+            }
+        }
+        stacks
+    }
+
+    // wears down the tool in hand, which breaks once its durability is used up
+    fn wear_selected(&mut self) {
+        let worn = match self.selected_item() {
+            Some(Tool(class, tier, wear)) if wear + 1 < tier.durability() =>
+                Some(Tool(class, tier, wear + 1)),
+            Some(Tool(*)) => None,
+            _ => return
+        };
+        self.slots[self.selected] = match worn {
+            Some(item) => Some(ItemStack { item: item, count: 1 }),
+            None => None
+        };
+    }
+
+    // removes one item from the selected stack
+    fn take_selected(&mut self) -> Option<Item> {
+        let (item, empty) = match self.slots[self.selected] {
+            Some(ref mut stack) => {
+                stack.count -= 1;
+                (stack.item, stack.count == 0)
+            },
+            None => return None
+        };
+        if empty {
+            self.slots[self.selected] = None;
+        }
+        Some(item)
+    }
+
+    fn select(&mut self, slot: uint) {
+        if slot < HOTBAR_SIZE {
+            self.selected = slot;
+        }
+    }
+
+    // moves the selection by steps slots, wrapping around the hotbar
+    fn scroll(&mut self, steps: int) {
+        let n = HOTBAR_SIZE as int;
+        self.selected = (((self.selected as int + steps) % n + n) % n) as uint;
+    }
+}
@@ -0,0 +1,92 @@
+use chunk;
+use daytime;
+use world;
+use world::World;
+use coords::BlockPos;
+use core::hashmap::HashSet;
+
+pub static MAX_LIGHT: uint = 15;
+static TORCH_LIGHT: uint = 14;
+
+/* Light from the sky when nothing opaque is anywhere above the block, none otherwise. It's
+ * brightest during the day and dims at night.
+ */
+pub fn sky_light(world: &World, pos: BlockPos) -> uint {
+    let mut above = pos.up();
+    while above.y <= world::BUILD_LIMIT {
+        match world.block_at(above) {
+            Some(b) if b.is_opaque() => return 0,
+            Some(_) => above = above.up(),
+            None => break
+        }
+    }
+    (MAX_LIGHT as float * daytime::sky_light_factor(world.time)) as uint
+}
+
+/* Light from torches, fading by one level for every block walked away from them. It spreads
+ * only through blocks that aren't opaque, so walls keep it out; the nearest torch is found by
+ * searching outwards from the block.
+ */
+pub fn block_light(world: &World, pos: BlockPos) -> uint {
+    let reach = TORCH_LIGHT as int;
+    let mut seen = HashSet::new();
+    seen.insert(pos);
+    let mut frontier = ~[pos];
+
+    for int::range(0, reach) |distance| {
+        let mut next = ~[];
+        for frontier.each |&p| {
+            match world.block_at(p) {
+                Some(&chunk::Torch) => return (reach - distance) as uint,
+                Some(b) if !b.is_opaque() => (),
+                _ => loop
+            }
+            for p.each_neighbour |n| {
+                if seen.insert(n) { next.push(n); }
+            }
+        }
+        frontier = next;
+    }
+    0
+}
+
+pub fn light_level(world: &World, pos: BlockPos) -> uint {
+    uint::max(sky_light(world, pos), block_light(world, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use chunk;
+    use coords::BlockPos;
+    use light::*;
+    use world;
+    use world::World;
+
+    // a torch at (2, 1, 8) on a stone floor, with a full-height wall at x = 5 if walled
+    fn torch_world(walled: bool) -> World {
+        let mut blocks = ~[(BlockPos::new(2, 1, 8), chunk::Torch)];
+        if walled {
+            for int::range(1, 16) |y| {
+                for int::range(0, 16) |z| {
+                    blocks.push((BlockPos::new(5, y, z), chunk::Stone));
+                }
+            }
+        }
+        world::test_world(world::new_test_chunk(), blocks)
+    }
+
+    #[test]
+    fn torch_light_fades_with_distance() {
+        let world = torch_world(false);
+        assert_eq!(block_light(&world, BlockPos::new(2, 1, 8)), 14);
+        assert_eq!(block_light(&world, BlockPos::new(8, 1, 8)), 8);
+        assert_eq!(block_light(&world, BlockPos::new(8, 3, 10)), 4);
+    }
+
+    #[test]
+    fn walls_keep_torch_light_out() {
+        let world = torch_world(true);
+        assert_eq!(block_light(&world, BlockPos::new(4, 1, 8)), 12);
+        assert_eq!(block_light(&world, BlockPos::new(8, 1, 8)), 0);
+    }
+}
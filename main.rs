@@ -5,12 +5,31 @@ use shader::Program;
 use texture;
 use texture::Texture;
 use chunk;
+use world;
 use world::World;
+use daytime;
+use worldgen;
 use font::Font;
+use sky::Sky;
+use hud;
+use hud::CraftingScreen;
+use item;
+use item::ItemStack;
+use registry::Registry;
+use crafting;
+use crafting::Recipe;
 
 use common::*;
+use coords::BlockPos;
+use aabb::Aabb;
+use player::{Player, Speeds, InputState};
+use hunger;
+use hunger::HungerConfig;
+use config;
+use config::Config;
 
 use lmath;
+use core::util;
 
 fn error_cb(_error: libc::c_int, desc: ~str) {
     println(fmt!("GLFW error: %s", desc));
@@ -31,16 +50,31 @@ fn debug_cb(_source: u32, _type: u32, _id: u32, _severity: u32, length: u32,
     println(string);
 }
 
-static MOVE_SPEED: float = 5.0f;
+// how far away blocks can be mined and placed
+static REACH: float = 5.0f;
+// how close items have to be to the player to be picked up
+static PICKUP_RANGE: float = 0.5f;
 
 fn main() {
+    let args = os::args();
+    if args.len() > 1 && args[1] == ~"--ore-stats" {
+        let radius = if args.len() > 2 { int::from_str(args[2]).get_or_default(2) } else { 2 };
+        worldgen::print_ore_stats(world::WORLD_SEED, radius);
+        return
+    }
+
     glfw::set_error_callback(error_cb);
 
     do glfw::spawn {
         let wnd = glfw::Window::create(1280, 800, "Kato moro", glfw::Windowed).unwrap();
 
         wnd.make_context_current();
-        wnd.set_key_callback(key_cb);
+        // keys pressed since the last frame, for keys that act once per press
+        let pressed_keys = @mut ~[];
+        do wnd.set_key_callback |window, key, action| {
+            key_cb(window, key, action);
+            if action == glfw::PRESS { pressed_keys.push(key); }
+        }
         wnd.set_input_mode(glfw::CURSOR_MODE, glfw::CURSOR_CAPTURED as int);
         wnd.set_input_mode(glfw::STICKY_MOUSE_BUTTONS, GL_TRUE as int);
 
@@ -51,29 +85,39 @@ fn main() {
         glEnable(GL_CULL_FACE);
         glEnable(GL_DEPTH_TEST);
         glDepthFunc(GL_LEQUAL);
-        glClearColor(0.53, 0.81, 0.98, 1.0);
 
+        let config = Config::load(&path::Path(config::CONFIG_FILE));
+        let registry = Registry::new();
+
+        let world = World::new();
+        let spawn = world.spawn_point();
         let mut game = GameState {
-            world: World::new(),
-            player: Player {
-                position: Vec3f::new(8.0, 1.0, 8.0),
-                rot_x: 0.0, rot_y: 0.0,
-                vel_y: 0.0,
-                mining_target: None
-            },
-            left_button_state: false
+            world: world,
+            player: Player::new(spawn, Speeds::from_config(&config),
+                                HungerConfig::from_config(&config)),
+            left_button_state: false,
+            spectator_key_state: false,
+            recipes: crafting::load_recipes(&path::Path(crafting::RECIPE_FILE), &registry),
+            crafting: None
         };
 
         let mut state = initialize_opengl();
         let mut camera = CameraState {
-            position: game.player.position.add_v(&Vec3f::new(0.0, 2.5, 0.0)),
+            position: game.player.eye_position(),
             rotation: Quatf::identity()
         };
 
         println("-- INITIALIZED --");
 
+        // scrolled hotbar slots since the last frame
+        let scrolled = @mut 0.0;
+        do wnd.set_scroll_callback |_, _, y| {
+            *scrolled += y;
+        }
+
         let mut last_cursor = wnd.get_cursor_pos();
         let mut last_update = glfw::get_time();
+        let mut tick_time = 0.0;
 
         while !wnd.should_close() {
             glfw::poll_events();
@@ -82,110 +126,62 @@ fn main() {
             let dt = (time - last_update) as float;
             last_update = time;
 
-            let rot_hori = Quatf::from_angle_axis(game.player.rot_x, &Vec3f::new(0.0, 1.0, 0.0));
-            let rot_vert = Quatf::from_angle_axis(game.player.rot_y, &Vec3f::new(1.0, 0.0, 0.0));
-
-            let plane_fwd = rot_hori.mul_v(&Vec3f::new(0.0, 0.0, -1.0));
-            let fwd       = camera.rotation.mul_v(&Vec3f::new(0.0, 0.0, -1.0));
-            let up        = camera.rotation.mul_v(&Vec3f::new(0.0, 1.0, 0.0));
-            let rt        = fwd.cross(&up);
-
-            /* collision detection now works, but if we get too close, the
-             * the wall is clipped by the near clipping plane
-             */
-
-            let mut target_pos = Vec3f::zero();
-            if wnd.get_key(glfw::KEY_A) == glfw::PRESS {
-                target_pos.add_self_v(&rt.mul_t(-dt*MOVE_SPEED));
-            }
-            if wnd.get_key(glfw::KEY_D) == glfw::PRESS {
-                target_pos.add_self_v(&rt.mul_t(dt*MOVE_SPEED));
+            for pressed_keys.each |&key| {
+                handle_key(&mut game, key);
             }
-            if wnd.get_key(glfw::KEY_W) == glfw::PRESS {
-                target_pos.add_self_v(&plane_fwd.mul_t(dt*MOVE_SPEED));
-            }
-            if wnd.get_key(glfw::KEY_S) == glfw::PRESS {
-                target_pos.add_self_v(&plane_fwd.mul_t(-dt*MOVE_SPEED));
-            }
-
-
-            let stop_fall;
-            game.player.vel_y -= 30.0 * dt;
-            {
-            let below_pos = Vec3f::new(game.player.position.x, game.player.position.y-0.001,
-                                          game.player.position.z);
-            let block_below = game.world.block_at_vec(&below_pos);
-            stop_fall =
-                match block_below {
-                    Some(&chunk::Air) => false,
-                    _ => true
-                };
+            pressed_keys.clear();
+
+            // the player stands still while a screen is open
+            let playing = game.crafting.is_none();
+            let input = if !playing { InputState::new() } else { InputState {
+                forward: wnd.get_key(glfw::KEY_W) == glfw::PRESS,
+                back: wnd.get_key(glfw::KEY_S) == glfw::PRESS,
+                left: wnd.get_key(glfw::KEY_A) == glfw::PRESS,
+                right: wnd.get_key(glfw::KEY_D) == glfw::PRESS,
+                jump: wnd.get_key(glfw::KEY_SPACE) == glfw::PRESS,
+                sneak: wnd.get_key(glfw::KEY_LEFT_SHIFT) == glfw::PRESS,
+                sprint: wnd.get_key(glfw::KEY_LEFT_CONTROL) == glfw::PRESS
+            }};
+
+            for uint::range(0, item::HOTBAR_SIZE) |i| {
+                if wnd.get_key(glfw::KEY_1 + i as libc::c_int) == glfw::PRESS {
+                    game.player.inventory.select(i);
+                }
             }
-            if stop_fall { game.player.vel_y = 0.0; }
-
-            if wnd.get_key(glfw::KEY_SPACE) == glfw::PRESS && stop_fall {
-                game.player.vel_y = 8.0;
+            // scrolling down moves to the right
+            game.player.inventory.scroll(-(*scrolled as int));
+            *scrolled -= (*scrolled as int) as f64;
+
+            match wnd.get_key(glfw::KEY_N) {
+                glfw::PRESS if !game.spectator_key_state => {
+                    game.spectator_key_state = true;
+                    game.player.toggle_spectator();
+                },
+                glfw::RELEASE => game.spectator_key_state = false,
+                _ => ()
             }
 
-            let target_xv = Vec3f::new(target_pos.x, 0.0, 0.0);
-            let target_yv = Vec3f::new(0.0, game.player.vel_y*dt, 0.0);
-            let target_zv = Vec3f::new(0.0, 0.0, target_pos.z);
-
-            fn floor(x: float) -> float {
-                float::floor(x as f64) as float
-            }
+            tick_time += dt;
+            while tick_time >= 1.0 / world::TICKS_PER_SECOND {
+                game.world.tick(&mut game.player);
+                game.player.tick(&game.world, &input);
 
-            let handle_x = || {
-                let abs_xv = game.player.position.add_v(&target_xv);
-                let rem_xm = if target_xv.x < 0.0 { floor(game.player.position.x) - game.player.position.x }
-                             else { floor(game.player.position.x) - game.player.position.x + 0.9999 };
+                let reach = game.player.aabb().grow(PICKUP_RANGE);
+                let inventory = &mut game.player.inventory;
+                do game.world.collect_items(&reach) |stack| {
+                    inventory.add(stack.item, stack.count)
+                }
 
-                game.player.position.add_self_v(&
-                    match game.world.block_at_vec(&abs_xv) {
-                        Some(&chunk::Air) => target_xv,
-                        _ => Vec3f::new(rem_xm, 0.0, 0.0)
+                if game.player.is_dead() {
+                    let stacks = game.player.inventory.take_all();
+                    for stacks.each |&stack| {
+                        game.world.drop_item(stack, &game.player.position);
                     }
-                );
-            };
-
-            let handle_z = || {
-                let abs_zv = game.player.position.add_v(&target_zv);
-                let rem_zm = if target_zv.z < 0.0 { floor(game.player.position.z) - game.player.position.z }
-                             else { floor(game.player.position.z) - game.player.position.z + 0.9999 };
-
-                game.player.position.add_self_v(&
-                    match game.world.block_at_vec(&abs_zv) {
-                        Some(&chunk::Air) => target_zv,
-                        _ => Vec3f::new(0.0, 0.0, rem_zm)
-                    }
-                );
-            };
-
-            let abs_yv = game.player.position.add_v(&target_yv);
-            let rem_ym = if target_yv.y < 0.0 { floor(game.player.position.y) - game.player.position.y }
-                         else { floor(game.player.position.y) - game.player.position.y + 0.9999 };
-
-            game.player.position.add_self_v(&
-                match game.world.block_at_vec(&abs_yv) {
-                    Some(&chunk::Air) => target_yv,
-                    _ => Vec3f::new(0.0, rem_ym, 0.0)
+                    game.player.respawn(game.world.spawn_point());
                 }
-            );
-
-            /* This is so that when jumping in a corner, we go the direction the player is pointing
-             * at more
-             */
-            if float::abs(target_pos.dot(&Vec3f::new(1.0, 0.0, 0.0))) >
-                 float::abs(target_pos.dot(&Vec3f::new(0.0, 0.0, 1.0)))
-            {
-                handle_x();
-                handle_z();
-            } else {
-                handle_z();
-                handle_x();
+                tick_time -= 1.0 / world::TICKS_PER_SECOND;
             }
 
-
             let cursor = wnd.get_cursor_pos();
             let (dx, dy) = match (cursor, last_cursor) { ((a,b),(c,d)) => (a-c,b-d) };
             last_cursor = cursor;
@@ -193,17 +189,22 @@ fn main() {
             game.player.rot_x -= (dx as float / 2800.0) * (3.1416 / 2.0);
             game.player.rot_y -= (dy as float / 3800.0) * (3.1416 / 2.0);
 
+            let rot_hori = Quatf::from_angle_axis(game.player.rot_x, &Vec3f::new(0.0, 1.0, 0.0));
+            let rot_vert = Quatf::from_angle_axis(game.player.rot_y, &Vec3f::new(1.0, 0.0, 0.0));
             camera.rotation = rot_hori.mul_q(&rot_vert);
-            camera.position = game.player.position.add_v(&Vec3f::new(0.0, 1.85, 0.0));
+            // render between the last two ticks so movement is smooth at any frame rate
+            camera.position = game.player.interpolated_eye_position(
+                tick_time * world::TICKS_PER_SECOND);
+            let fwd = camera.rotation.mul_v(&Vec3f::new(0.0, 0.0, -1.0));
 
-            if wnd.get_mouse_button(glfw::MOUSE_BUTTON_LEFT) == glfw::PRESS {
-                // ugh
+            if playing && wnd.get_mouse_button(glfw::MOUSE_BUTTON_LEFT) == glfw::PRESS {
                 let replace =
-                match game.world.cast_ray(
-                    &game.player.position.add_v(&Vec3f::new(0.0, 1.85, 0.0)), &fwd)
+                match game.world.raycast(
+                    &game.player.eye_position(), &fwd, REACH)
                 {
-                    Some((cc,_)) => Some(cc),
-                    None => None
+                    // bedrock and the like can't be mined even in creative
+                    Some(hit) if !hit.block.is_protected() => Some(hit.pos),
+                    _ => None
                 };
                 game.player.mining_target =
                 match (replace, game.player.mining_target) {
@@ -219,21 +220,37 @@ fn main() {
             }
 
             match wnd.get_mouse_button(glfw::MOUSE_BUTTON_RIGHT) {
-                glfw::PRESS if game.left_button_state == false => {
+                glfw::PRESS if playing && game.left_button_state == false => {
                     game.left_button_state = true;
-                    let mut replace = None;
-                    {
-                        let block = game.world.cast_ray_previous(
-                            &game.player.position.add_v(&Vec3f::new(0.0, 1.85, 0.0)), &fwd);
-                        match block {
-                            None => (),
-                            Some((cc, _)) => replace = Some(cc)
+                    let hit = game.world.raycast(&game.player.eye_position(), &fwd, REACH);
+                    let used_door = match hit {
+                        Some(hit) => game.world.toggle_door(hit.pos),
+                        None => false
+                    };
+                    // doors are used and food in hand is eaten instead of placing a block
+                    if !used_door && !try_eat(&mut game.player) {
+                        // place against the face that was hit
+                        let replace = match hit {
+                            // don't place blocks inside the player
+                            Some(hit) if hit.normal != BlockPos::new(0, 0, 0) &&
+                                         !Aabb::block(hit.pos + hit.normal).intersects(
+                                             &game.player.aabb()) =>
+                                Some(hit.pos + hit.normal),
+                            _ => None
+                        };
+                        let block = match game.player.inventory.selected_item() {
+                            Some(item) => item.as_block(),
+                            None => None
+                        };
+                        match (replace, block) {
+                            (Some(cc), Some(block)) => match game.world.replace_block(cc, block) {
+                                Ok(()) if game.player.is_creative() => (),
+                                Ok(()) => { game.player.inventory.take_selected(); },
+                                Err(e) => println(fmt!("cannot place at %?: %?", cc, e))
+                            },
+                            _ => ()
                         }
                     }
-                    match replace {
-                        None => (),
-                        Some(cc) => game.world.replace_block(cc, chunk::Stone)
-                    }
                 },
                 glfw::RELEASE => {
                     game.left_button_state = false;
@@ -244,21 +261,49 @@ fn main() {
             match game.player.mining_target {
                 None => (),
                 Some((cc, start)) => {
-                    if glfw::get_time() as float - start > game.world.block_at(cc).unwrap().breaking_time() {
-                        game.world.replace_block(cc, chunk::Air);
+                    let held = game.player.inventory.selected_item();
+                    let creative = game.player.is_creative();
+                    let (breaking_time, hardness) = match game.world.block_at(cc) {
+                        Some(_) if creative => (0.0, 0.0),
+                        Some(&b) => (item::mining_time(b, held), b.hardness()),
+                        None => (float::infinity, 0.0)
+                    };
+                    if glfw::get_time() as float - start > breaking_time {
+                        // nothing is dropped in creative mode
+                        let result = if creative {
+                            game.world.replace_block(cc, chunk::Air)
+                        } else {
+                            game.world.break_block(cc, held)
+                        };
+                        match result {
+                            Ok(()) => {
+                                let exhaustion = game.player.hunger.config.mining_exhaustion;
+                                game.player.exhaust(exhaustion);
+                                // blocks that break instantly don't wear tools
+                                if hardness > 0.0 { game.player.inventory.wear_selected(); }
+                            },
+                            Err(e) => println(fmt!("cannot mine %?: %?", cc, e))
+                        }
+                        game.player.mining_target = None;
                     }
                 }
             }
 
+            game.world.update_meshes();
             draw(&mut state, &camera, &game);
 
             wnd.swap_buffers();
         }
+
+        game.world.save();
     }
 }
 
 struct RendererState {
     program: Program,
+    projection: Mat4f,
+    sky: Sky,
+    block_mesh: chunk::BlockMesh,
     brick_tex: Texture,
     font: Font
 }
@@ -268,41 +313,117 @@ struct CameraState {
     rotation: Quatf
 }
 
-struct Player {
-    position: Vec3f,
-    rot_x: float,
-    rot_y: float,
-    vel_y: float,
-    mining_target: Option<((int,int,int), float)>
-}
-
 struct GameState {
     world: World,
     player: Player,
-    left_button_state: bool
+    left_button_state: bool,
+    spectator_key_state: bool,
+    recipes: ~[Recipe],
+    // the crafting screen, if it's open
+    crafting: Option<CraftingScreen>
+}
+
+fn handle_key(game: &mut GameState, key: libc::c_int) {
+    if key == glfw::KEY_G {
+        game.player.toggle_game_mode();
+        if game.player.is_creative() {
+            game.player.inventory.fill_hotbar(item::creative_palette);
+        }
+        return
+    }
+
+    if key == glfw::KEY_E {
+        match util::replace(&mut game.crafting, None) {
+            // closing gives back whatever was left in the grid
+            Some(screen) => {
+                let mut screen = screen;
+                for screen.grid.clear().each |stack| {
+                    give(game, stack);
+                }
+            },
+            None => game.crafting = Some(CraftingScreen::new())
+        }
+        return
+    }
+
+    // items taken out of the grid or crafted, for the player
+    let taken = {
+        let screen = match game.crafting {
+            Some(ref mut screen) => screen,
+            None => return
+        };
+        let inventory = &mut game.player.inventory;
+
+        match key {
+            glfw::KEY_LEFT => { screen.move_cursor(-1, 0); None },
+            glfw::KEY_RIGHT => { screen.move_cursor(1, 0); None },
+            glfw::KEY_UP => { screen.move_cursor(0, -1); None },
+            glfw::KEY_DOWN => { screen.move_cursor(0, 1); None },
+            glfw::KEY_Q => {
+                match inventory.selected_item() {
+                    Some(item) => if screen.grid.put(screen.cursor, item) {
+                        inventory.take_selected();
+                    },
+                    None => ()
+                }
+                None
+            },
+            glfw::KEY_BACKSPACE => screen.grid.take(screen.cursor),
+            glfw::KEY_ENTER => screen.grid.craft(game.recipes),
+            _ => None
+        }
+    };
+
+    match taken {
+        Some(stack) => give(game, &stack),
+        None => ()
+    }
+}
+
+// eats the selected item if it's food and the player is hungry, returning whether they did
+fn try_eat(player: &mut Player) -> bool {
+    let food = match player.inventory.selected_item() {
+        Some(item) => item.food_value(),
+        None => None
+    };
+    match food {
+        Some(value) if player.hunger.food < hunger::MAX_FOOD => {
+            player.inventory.take_selected();
+            player.hunger.eat(value);
+            true
+        },
+        _ => false
+    }
+}
+
+// puts the stack in the player's inventory, dropping what doesn't fit
+fn give(game: &mut GameState, stack: &ItemStack) {
+    let left = game.player.inventory.add(stack.item, stack.count);
+    if left > 0 {
+        game.world.drop_item(ItemStack { item: stack.item, count: left },
+                             &game.player.eye_position());
+    }
 }
 
 fn initialize_opengl() -> RendererState {
     glViewport(0, 0, 1280, 800);
 
-    let mut program = Program::new(io::read_whole_file_str(&path::Path("shader.vert")).unwrap(),
+    let program = Program::new(io::read_whole_file_str(&path::Path("shader.vert")).unwrap(),
                                    io::read_whole_file_str(&path::Path("shader.frag")).unwrap());
 
     let projection = lmath::projection::perspective(67.5, 800.0 / 480.0, 0.1, 60.0);
-    program.set_uniform_mat4("projection", &projection);
 
     RendererState {
         program: program,
-        brick_tex: Texture::load_file(~"texes2.png", texture::TextureArray(4)).unwrap(),
+        projection: projection,
+        sky: Sky::new(),
+        block_mesh: chunk::BlockMesh::new(),
+        brick_tex: Texture::load_file(~"texes2.png",
+                                      texture::TextureArray(chunk::TEXTURE_LAYERS)).unwrap(),
         font: Font::new(~"font.png", ~"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz1234567890{}[]()<>$*-+=/#_%^@\\&|~?'\"!,.;:")
     }
 }
 
-fn translation_matrix(t: (float, float, float)) -> Mat4f {
-    let (x,y,z) = t;
-    Mat4f::new(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, x, y, z, 1.0)
-}
-
 fn draw(state: &mut RendererState, camera: &CameraState, game: &GameState) {
     let camera_matrix = Mat4f::new(1.0, 0.0, 0.0, 0.0,
                                    0.0, 1.0, 0.0, 0.0,
@@ -310,7 +431,8 @@ fn draw(state: &mut RendererState, camera: &CameraState, game: &GameState) {
                                    -camera.position.x, -camera.position.y,
                                      -camera.position.z, 1.0
                                   );
-    let camera_matrix = camera.rotation.inverse().to_mat3().to_mat4().mul_m(&camera_matrix);
+    let view = camera.rotation.inverse().to_mat3().to_mat4();
+    let camera_matrix = view.mul_m(&camera_matrix);
 
 
     let (x, y, z) = (0.0, 0.0, 0.0);
@@ -322,22 +444,55 @@ fn draw(state: &mut RendererState, camera: &CameraState, game: &GameState) {
     let modelview = camera_matrix.mul_m(&modelview);
 
     state.program.bind();
+    // the HUD replaces the projection, so set it every frame
+    state.program.set_uniform_mat4("projection", &state.projection);
     state.program.set_uniform_mat4("modelview", &modelview);
 
     state.brick_tex.bind(0);
     state.program.set_uniform_int("texture", 0);
 
+    let (light_direction, light_colour) = daytime::light(game.world.time);
+    state.program.set_uniform_vec3("sun_direction", &light_direction);
+    state.program.set_uniform_vec3("sun_colour", &light_colour);
+    state.program.set_uniform_float("daylight", daytime::daylight(game.world.time));
+
+    let sky = daytime::sky_colour(game.world.time);
+    glClearColor(sky.x as f32, sky.y as f32, sky.z as f32, 1.0);
     glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+    let projection = state.projection;
+    state.sky.draw(&projection, &view, &camera.position, game.world.time);
 
-    for game.world.each_chunk |&(x,y,z), chunk| {
+    for game.world.each_chunk |cc, chunk| {
         let modelview = camera_matrix.mul_m(&translation_matrix(
-            (x as float * 16.0,y as float * 16.0,z as float * 16.0)));
+            (cc.x as float * 16.0, cc.y as float * 16.0, cc.z as float * 16.0)));
         state.program.set_uniform_mat4("modelview", &modelview);
         chunk.draw_cached(&mut state.program);
     }
 
+    for game.world.each_entity |entity| {
+        entity.draw(&mut state.program, &state.block_mesh, &camera_matrix);
+    }
+
     let fwd = camera.rotation.mul_v(&Vec3f::new(0.0, 0.0, -1.0));
-    let target = game.world.cast_ray(&game.player.position.add_v(&Vec3f::new(0.0, 1.85, 0.0)), &fwd);
+    let target = game.world.raycast(&game.player.eye_position(), &fwd,
+                                    REACH);
+
+    // the HUD is always lit as at noon
+    state.program.set_uniform_vec3("sun_direction", &Vec3f::new(0.0, 1.0, 0.0));
+    state.program.set_uniform_vec3("sun_colour", &Vec3f::new(1.0, 1.0, 1.0));
+    state.program.set_uniform_float("daylight", 1.0);
+    hud::draw_hotbar(&mut state.program, &state.block_mesh, &state.brick_tex, &state.font,
+                     &game.player.inventory);
+    hud::draw_status(&state.font, &game.player);
+    match game.crafting {
+        Some(ref screen) => hud::draw_crafting(&mut state.program, &state.block_mesh,
+                                               &state.brick_tex, &state.font, screen,
+                                               game.recipes),
+        None => ()
+    }
 
-    state.font.draw(fmt!("T %?", target));
+    state.font.draw_at(match target {
+        Some(hit) => fmt!("T %d %d %d %s", hit.pos.x, hit.pos.y, hit.pos.z, hit.block.name()),
+        None => ~"T None"
+    }, 0.0, 19.0);
 }
@@ -0,0 +1,330 @@
+use chunk;
+use chunk::BlockMesh;
+use entity;
+use entity::{Entity, EntityAction, Keep, Remove, Attack};
+use light;
+use pathfind;
+use pathfind::PathOptions;
+use player::Player;
+use shader::Program;
+use world::World;
+use aabb::Aabb;
+
+use common::*;
+use coords::BlockPos;
+use core::float;
+use core::rand::RngUtil;
+use numeric::*;
+
+static MOB_JUMP_SPEED: float = 8.0;
+// a waypoint counts as reached this close to the middle of its block
+static WAYPOINT_DISTANCE: float = 0.3;
+// nodes the pathfinder may expand each time a mob decides where to go
+static PATH_BUDGET: uint = 400;
+// how far from where it stands a wandering mob picks its next goal
+static WANDER_RANGE: int = 6;
+static REPATH_TICKS: uint = 20;
+
+// hostile mobs notice players within this distance
+static FOLLOW_RANGE: float = 16.0;
+// hostile mobs further than this from the player are removed
+static DESPAWN_DISTANCE: float = 48.0;
+static ATTACK_REACH: float = 0.4;
+static ATTACK_DAMAGE: int = 3;
+static ATTACK_COOLDOWN: uint = 20;
+
+// ticks between spawn attempts
+static SPAWN_INTERVAL: u64 = 20;
+// mobs spawn in a square around the player, but not closer than the minimum on both axes
+static SPAWN_MIN_DISTANCE: int = 12;
+static SPAWN_MAX_DISTANCE: int = 24;
+// blocks searched up and down from the player's height for ground to spawn on
+static SPAWN_HEIGHT_RANGE: int = 16;
+static PASSIVE_MIN_LIGHT: uint = 9;
+static HOSTILE_MAX_LIGHT: uint = 7;
+// most mobs of each kind allowed in the 3x3 chunk columns around a spawn
+static PASSIVE_CAP: uint = 4;
+static HOSTILE_CAP: uint = 4;
+
+#[deriving(Eq)]
+pub enum MobKind {
+    // wanders around grassland
+    Pig,
+    // chases and attacks players in the dark
+    Zombie
+}
+
+pub impl MobKind {
+    fn is_hostile(&self) -> bool {
+        *self == Zombie
+    }
+
+    // width and height of the mob's box
+    fn size(&self) -> (float, float) {
+        match *self {
+            Pig => (0.9, 0.9),
+            Zombie => (0.6, 1.8)
+        }
+    }
+
+    fn speed(&self) -> float {
+        match *self {
+            Pig => 2.0,
+            Zombie => 3.5
+        }
+    }
+
+    fn to_id(&self) -> u8 {
+        match *self {
+            Pig => 0,
+            Zombie => 1
+        }
+    }
+
+    fn from_id(id: u8) -> Option<MobKind> {
+        match id {
+            0 => Some(Pig),
+            1 => Some(Zombie),
+            _ => None
+        }
+    }
+
+    // the boxes the mob is drawn with, relative to its feet and facing +z
+    fn model(&self) -> ~[(Vec3f, Vec3f, chunk::Block)] {
+        let part = |x: float, y: float, z: float, n: float, m: float, k: float, b: chunk::Block|
+            (Vec3f::new(x, y, z), Vec3f::new(n, m, k), b);
+        match *self {
+            Pig => ~[
+                part(0.0, 0.55, 0.0, 0.3, 0.25, 0.45, chunk::Dirt),
+                part(0.0, 0.65, 0.55, 0.2, 0.2, 0.15, chunk::Dirt),
+                part(-0.2, 0.15, -0.3, 0.08, 0.15, 0.08, chunk::Dirt),
+                part(0.2, 0.15, -0.3, 0.08, 0.15, 0.08, chunk::Dirt),
+                part(-0.2, 0.15, 0.3, 0.08, 0.15, 0.08, chunk::Dirt),
+                part(0.2, 0.15, 0.3, 0.08, 0.15, 0.08, chunk::Dirt)
+            ],
+            Zombie => ~[
+                part(-0.12, 0.375, 0.0, 0.12, 0.375, 0.12, chunk::Cobblestone),
+                part(0.12, 0.375, 0.0, 0.12, 0.375, 0.12, chunk::Cobblestone),
+                part(0.0, 1.125, 0.0, 0.25, 0.375, 0.13, chunk::Leaves),
+                part(0.0, 1.55, 0.0, 0.25, 0.25, 0.25, chunk::Grass),
+                // arms held out in front
+                part(-0.37, 1.35, 0.25, 0.12, 0.12, 0.37, chunk::Leaves),
+                part(0.37, 1.35, 0.25, 0.12, 0.12, 0.37, chunk::Leaves)
+            ]
+        }
+    }
+}
+
+// The state of a mob entity beyond what every entity has
+pub struct Mob {
+    kind: MobKind,
+    // blocks still to walk through, the next one first
+    path: ~[BlockPos],
+    // false while heading straight for the player because no path was found
+    path_found: bool,
+    // ticks until the mob next decides where to go
+    think_timer: uint,
+    attack_cooldown: uint,
+    // the direction the mob faces, in radians around the y axis
+    yaw: float
+}
+
+pub impl Mob {
+    fn new(kind: MobKind) -> Mob {
+        Mob { kind: kind, path: ~[], path_found: false, think_timer: 0, attack_cooldown: 0,
+              yaw: 0.0 }
+    }
+
+    fn aabb(&self, position: &Vec3f) -> Aabb {
+        let (width, height) = self.kind.size();
+        Aabb::from_feet(position, width, height)
+    }
+
+    fn path_options(&self) -> PathOptions {
+        let mut options = PathOptions::new();
+        let (_, height) = self.kind.size();
+        options.height = float::ceil(height as f64) as int;
+        options.max_iterations = PATH_BUDGET;
+        options
+    }
+
+    // whether the mob is after the player
+    fn chasing(&self, player: &Player, position: &Vec3f) -> bool {
+        self.kind.is_hostile() && !player.is_creative() &&
+            player.position.sub_v(position).length() < FOLLOW_RANGE
+    }
+
+    /* Decides where to go next: hostile mobs find a way to the player they're chasing,
+     * others now and then wander off to somewhere nearby.
+     */
+    fn think(&mut self, world: &World, player: &Player, position: &Vec3f) {
+        let start = BlockPos::from_vec(position);
+        let options = self.path_options();
+
+        if self.chasing(player, position) {
+            self.think_timer = REPATH_TICKS;
+            let goal = BlockPos::from_vec(&player.position);
+            match pathfind::find_path(world, start, goal, &options) {
+                Some(path) => {
+                    self.path = path;
+                    self.path_found = true;
+                },
+                // head straight for the player and hope for the best
+                None => {
+                    self.path = ~[goal];
+                    self.path_found = false;
+                }
+            }
+            return
+        }
+
+        self.think_timer = world.rng.gen_uint_range(60, 200);
+        self.path = ~[];
+        if world.rng.gen_bool() { return }
+
+        let around = start.offset(world.rng.gen_int_range(-WANDER_RANGE, WANDER_RANGE + 1), 0,
+                                  world.rng.gen_int_range(-WANDER_RANGE, WANDER_RANGE + 1));
+        for int::range(-2, 3) |dy| {
+            let goal = around.offset(0, dy, 0);
+            if !pathfind::can_stand(world, goal, &options) { loop }
+            match pathfind::find_path(world, start, goal, &options) {
+                Some(path) => {
+                    self.path = path;
+                    self.path_found = true;
+                },
+                None => ()
+            }
+            break
+        }
+    }
+
+    // the horizontal velocity taking the mob along its path, and whether it has to jump
+    fn follow_path(&mut self, position: &Vec3f) -> (Vec3f, bool) {
+        while !self.path.is_empty() {
+            let next = self.path[0];
+            let d = Vec3f::new(next.x as float + 0.5 - position.x, 0.0,
+                               next.z as float + 0.5 - position.z);
+            let distance = d.length();
+            let feet_y = float::floor(position.y as f64) as int;
+            if distance < WAYPOINT_DISTANCE && next.y <= feet_y {
+                self.path.shift();
+                loop
+            }
+
+            // right below the waypoint there's no direction to go in, only up
+            if distance < 1e-6 { return (Vec3f::zero(), next.y > feet_y) }
+
+            self.yaw = float::atan2(d.x, d.z);
+            // waypoints up a block need a jump, and so do those across a gap, which are the
+            // only ones a found path puts two blocks apart
+            let jump = next.y > feet_y || (self.path_found && distance > 1.5);
+            return (d.mul_t(self.kind.speed() / distance), jump)
+        }
+        (Vec3f::zero(), false)
+    }
+
+    // runs the mob's AI for a tick, setting the velocity it wants to move with
+    fn tick(&mut self, world: &World, player: &Player, position: &Vec3f, on_ground: bool,
+            velocity: &mut Vec3f) -> EntityAction
+    {
+        // passive mobs stay around, to be saved with their chunk
+        if self.kind.is_hostile() &&
+                player.position.sub_v(position).length() > DESPAWN_DISTANCE {
+            return Remove
+        }
+
+        if self.attack_cooldown > 0 { self.attack_cooldown -= 1; }
+        if self.think_timer > 0 {
+            self.think_timer -= 1;
+        } else {
+            self.think(world, player, position);
+        }
+
+        let (wanted, jump) = self.follow_path(position);
+        if on_ground {
+            velocity.x = wanted.x;
+            velocity.z = wanted.z;
+            if jump { velocity.y = MOB_JUMP_SPEED; }
+        }
+
+        if self.chasing(player, position) && self.attack_cooldown == 0 &&
+                self.aabb(position).grow(ATTACK_REACH).intersects(&player.aabb()) {
+            self.attack_cooldown = ATTACK_COOLDOWN;
+            return Attack(ATTACK_DAMAGE)
+        }
+        Keep
+    }
+
+    fn draw(&self, program: &mut Program, mesh: &BlockMesh, camera_matrix: &Mat4f,
+            position: &Vec3f)
+    {
+        let rotation = Quatf::from_angle_axis(self.yaw, &Vec3f::new(0.0, 1.0, 0.0));
+        let modelview = camera_matrix.mul_m(&translation_matrix(
+            (position.x, position.y, position.z))).mul_m(&rotation.to_mat3().to_mat4());
+        mesh.draw_boxes(program, &modelview, self.kind.model());
+    }
+}
+
+// the first spot a mob could stand at in the column, searching down from above the player
+fn find_ground(world: &World, column: BlockPos) -> Option<BlockPos> {
+    let options = PathOptions::new();
+    for int::range(0, 2 * SPAWN_HEIGHT_RANGE) |i| {
+        let pos = column.offset(0, SPAWN_HEIGHT_RANGE - i, 0);
+        let dry = match world.block_at(pos) {
+            Some(b) => !b.is_fluid(),
+            None => false
+        };
+        if dry && pathfind::can_stand(world, pos, &options) { return Some(pos) }
+    }
+    None
+}
+
+// counts the hostile or passive mobs in the chunk columns next to and including pos's
+fn mobs_near(world: &World, pos: BlockPos, hostile: bool) -> uint {
+    let cc = pos.chunk();
+    let mut count = 0;
+    for world.each_entity |e| {
+        match e.kind {
+            entity::Mob(ref mob) if mob.kind.is_hostile() == hostile => {
+                let ec = BlockPos::from_vec(&e.position).chunk();
+                if int::abs(ec.x - cc.x) <= 1 && int::abs(ec.z - cc.z) <= 1 { count += 1; }
+            },
+            _ => ()
+        }
+    }
+    count
+}
+
+/* Now and then tries to spawn a mob at a random spot around the player. Hostile mobs spawn
+ * in the dark and passive ones on lit grass, each up to their cap.
+ */
+pub fn spawn_mobs(world: &mut World, player: &Player) {
+    if world.ticks % SPAWN_INTERVAL != 0 { return }
+
+    let (dx, dz) = (world.rng.gen_int_range(-SPAWN_MAX_DISTANCE, SPAWN_MAX_DISTANCE + 1),
+                    world.rng.gen_int_range(-SPAWN_MAX_DISTANCE, SPAWN_MAX_DISTANCE + 1));
+    if int::abs(dx) < SPAWN_MIN_DISTANCE && int::abs(dz) < SPAWN_MIN_DISTANCE { return }
+
+    let column = BlockPos::from_vec(&player.position).offset(dx, 0, dz);
+    let pos = match find_ground(world, column) {
+        Some(pos) => pos,
+        None => return
+    };
+
+    let light = light::light_level(world, pos);
+    let on_grass = match world.block_at(pos.down()) {
+        Some(&chunk::Grass) => true,
+        _ => false
+    };
+    let (kind, cap) = if light <= HOSTILE_MAX_LIGHT {
+        (Zombie, HOSTILE_CAP)
+    } else if light >= PASSIVE_MIN_LIGHT && on_grass {
+        (Pig, PASSIVE_CAP)
+    } else {
+        return
+    };
+    if mobs_near(world, pos, kind.is_hostile()) >= cap { return }
+
+    let position = Vec3f::new(pos.x as float + 0.5, pos.y as float, pos.z as float + 0.5);
+    world.spawn_entity(Entity::new(entity::Mob(Mob::new(kind)), position));
+}
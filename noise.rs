@@ -32,8 +32,9 @@ struct Noise2DContext {
 }
 
 pub impl Noise2DContext {
-    fn new() -> Noise2DContext {
-        let r = rand::seeded_rng(&[1,2,8,44,1223,66,123,67789,22,5535]);
+    fn new(seed: uint) -> Noise2DContext {
+        let r = rand::seeded_rng(&[seed as u8, (seed >> 8) as u8, (seed >> 16) as u8,
+                                   (seed >> 24) as u8, 1, 2, 8, 44]);
         let mut rgradients = [ Vec2 { x: 0.0, y: 0.0 }, ..256 ];
         for int::range(0, 256) |i| { rgradients[i] = random_gradient(r); }
         let mut permutations = [ 0, ..256 ];
@@ -89,3 +90,75 @@ pub impl Noise2DContext {
         lerp(vx0, vx1, fy)
     }
 }
+
+struct Vec3 {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+// the twelve edge midpoints of a cube; plain Perlin gradients work well enough for caves
+static gradients3: [Vec3, ..12] = [
+    Vec3 { x: 1.0, y: 1.0, z: 0.0 }, Vec3 { x:-1.0, y: 1.0, z: 0.0 },
+    Vec3 { x: 1.0, y:-1.0, z: 0.0 }, Vec3 { x:-1.0, y:-1.0, z: 0.0 },
+    Vec3 { x: 1.0, y: 0.0, z: 1.0 }, Vec3 { x:-1.0, y: 0.0, z: 1.0 },
+    Vec3 { x: 1.0, y: 0.0, z:-1.0 }, Vec3 { x:-1.0, y: 0.0, z:-1.0 },
+    Vec3 { x: 0.0, y: 1.0, z: 1.0 }, Vec3 { x: 0.0, y:-1.0, z: 1.0 },
+    Vec3 { x: 0.0, y: 1.0, z:-1.0 }, Vec3 { x: 0.0, y:-1.0, z:-1.0 },
+];
+
+#[inline(always)]
+fn dot3(grad: Vec3, x: f32, y: f32, z: f32) -> f32 {
+    grad.x * x + grad.y * y + grad.z * z
+}
+
+struct Noise3DContext {
+    permutations: [int, ..512],
+}
+
+pub impl Noise3DContext {
+    fn new(seed: uint) -> Noise3DContext {
+        let r = rand::seeded_rng(&[seed as u8, (seed >> 8) as u8, (seed >> 16) as u8,
+                                   (seed >> 24) as u8, 3, 141, 59, 26]);
+        let mut perm = [ 0, ..256 ];
+        for int::range(0, 256) |i| { perm[i] = i; }
+        r.shuffle_mut(perm);
+
+        // doubled so that lookups never need to wrap
+        let mut permutations = [ 0, ..512 ];
+        for int::range(0, 512) |i| { permutations[i] = perm[i & 255]; }
+
+        Noise3DContext {
+            permutations: permutations,
+        }
+    }
+
+    #[inline(always)]
+    fn get_gradient(&self, x: int, y: int, z: int) -> Vec3 {
+        let p = &self.permutations;
+        gradients3[p[p[p[x & 255] + (y & 255)] + (z & 255)] % 12]
+    }
+
+    // returns a value roughly in the range -1..1
+    fn get(&self, x: f32, y: f32, z: f32) -> f32 {
+        let x0f = f32::floor(x);
+        let y0f = f32::floor(y);
+        let z0f = f32::floor(z);
+        let (x0, y0, z0) = (x0f as int, y0f as int, z0f as int);
+        let (fx, fy, fz) = (x - x0f, y - y0f, z - z0f);
+
+        let v000 = dot3(self.get_gradient(x0,   y0,   z0  ), fx,       fy,       fz);
+        let v100 = dot3(self.get_gradient(x0+1, y0,   z0  ), fx - 1.0, fy,       fz);
+        let v010 = dot3(self.get_gradient(x0,   y0+1, z0  ), fx,       fy - 1.0, fz);
+        let v110 = dot3(self.get_gradient(x0+1, y0+1, z0  ), fx - 1.0, fy - 1.0, fz);
+        let v001 = dot3(self.get_gradient(x0,   y0,   z0+1), fx,       fy,       fz - 1.0);
+        let v101 = dot3(self.get_gradient(x0+1, y0,   z0+1), fx - 1.0, fy,       fz - 1.0);
+        let v011 = dot3(self.get_gradient(x0,   y0+1, z0+1), fx,       fy - 1.0, fz - 1.0);
+        let v111 = dot3(self.get_gradient(x0+1, y0+1, z0+1), fx - 1.0, fy - 1.0, fz - 1.0);
+
+        let (sx, sy, sz) = (smooth(fx), smooth(fy), smooth(fz));
+        let vy0 = lerp(lerp(v000, v100, sx), lerp(v010, v110, sx), sy);
+        let vy1 = lerp(lerp(v001, v101, sx), lerp(v011, v111, sx), sy);
+        lerp(vy0, vy1, sz)
+    }
+}
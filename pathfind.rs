@@ -0,0 +1,309 @@
+use chunk;
+use world::World;
+
+use common::*;
+use coords::{BlockPos, horizontal_offsets};
+use core::hashmap::{HashMap, HashSet};
+use core::util;
+use numeric::*;
+
+// What the walker looking for a path is able to do
+pub struct PathOptions {
+    // blocks of free space the walker needs above its feet
+    height: int,
+    // furthest drop the path may lead down
+    max_fall: int,
+    // whether paths may go through closed doors; opening them is up to the walker
+    open_doors: bool,
+    // nodes expanded before giving up
+    max_iterations: uint
+}
+
+pub impl PathOptions {
+    fn new() -> PathOptions {
+        PathOptions { height: 2, max_fall: 3, open_doors: false, max_iterations: 2000 }
+    }
+}
+
+// whether the walker can be inside the block
+fn passable(world: &World, pos: BlockPos, options: &PathOptions) -> bool {
+    match world.block_at(pos) {
+        Some(&chunk::Door(_)) if options.open_doors => true,
+        Some(&chunk::Lava(_)) => false,
+        Some(b) => !b.blocks(),
+        None => false
+    }
+}
+
+// whether the walker fits with its feet in the block
+fn fits(world: &World, pos: BlockPos, options: &PathOptions) -> bool {
+    for int::range(0, options.height) |dy| {
+        if !passable(world, pos.offset(0, dy, 0), options) { return false }
+    }
+    true
+}
+
+fn supported(world: &World, pos: BlockPos) -> bool {
+    match world.block_at(pos.down()) {
+        Some(b) => b.blocks(),
+        None => false
+    }
+}
+
+pub fn can_stand(world: &World, pos: BlockPos, options: &PathOptions) -> bool {
+    fits(world, pos, options) && supported(world, pos)
+}
+
+fn distance(a: BlockPos, b: BlockPos) -> float {
+    a.to_vec().sub_v(&b.to_vec()).length()
+}
+
+/* The places the walker can get to from pos in one move, with what it costs: walking to a
+ * neighbour, stepping up one block, falling down at most max_fall blocks or jumping over a
+ * one block gap. Costs are never less than the distance covered.
+ */
+fn moves(world: &World, pos: BlockPos, options: &PathOptions) -> ~[(BlockPos, float)] {
+    let mut moves = ~[];
+    // jumping needs a free block above the walker's head
+    let headroom = passable(world, pos.offset(0, options.height, 0), options);
+
+    for horizontal_offsets.each |&d| {
+        let next = pos + d;
+
+        if !fits(world, next, options) {
+            if headroom && can_stand(world, next.up(), options) {
+                moves.push((next.up(), 1.5));
+            }
+            loop
+        }
+        if supported(world, next) {
+            moves.push((next, 1.0));
+            loop
+        }
+
+        for int::range(1, options.max_fall + 1) |fall| {
+            let below = next.offset(0, -fall, 0);
+            if !passable(world, below, options) { break }
+            if supported(world, below) {
+                moves.push((below, 1.0 + fall as float));
+                break
+            }
+        }
+
+        let far = next + d;
+        if headroom && passable(world, next.offset(0, options.height, 0), options) &&
+                can_stand(world, far, options) {
+            moves.push((far, 2.5));
+        }
+    }
+
+    moves
+}
+
+/* The nodes waiting to be expanded, as a binary min-heap on each node's estimated total
+ * cost. Hand-written since the std priority queue is a max-heap over Ord, which floats
+ * aren't.
+ */
+struct OpenList {
+    nodes: ~[(float, BlockPos)]
+}
+
+impl OpenList {
+    fn new() -> OpenList {
+        OpenList { nodes: ~[] }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn cost(&self, i: uint) -> float {
+        let (cost, _) = self.nodes[i];
+        cost
+    }
+
+    fn push(&mut self, cost: float, pos: BlockPos) {
+        self.nodes.push((cost, pos));
+
+        let mut i = self.nodes.len() - 1;
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.cost(parent) <= self.cost(i) { break }
+            vec::swap(self.nodes, i, parent);
+            i = parent;
+        }
+    }
+
+    // removes and returns the cheapest node; the list must not be empty
+    fn pop(&mut self) -> BlockPos {
+        let last = self.nodes.pop();
+        if self.nodes.is_empty() {
+            let (_, pos) = last;
+            return pos
+        }
+        let (_, top) = util::replace(&mut self.nodes[0], last);
+
+        let n = self.nodes.len();
+        let mut i = 0;
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut smallest = i;
+            if left < n && self.cost(left) < self.cost(smallest) { smallest = left }
+            if right < n && self.cost(right) < self.cost(smallest) { smallest = right }
+            if smallest == i { break }
+            vec::swap(self.nodes, i, smallest);
+            i = smallest;
+        }
+        top
+    }
+}
+
+/* Looks for a way to walk from start to goal, both being the block the walker's feet are in.
+ * Returns the blocks to go through in order, ending with the goal, or None if there is no
+ * path or none was found within options.max_iterations.
+ */
+pub fn find_path(world: &World, start: BlockPos, goal: BlockPos, options: &PathOptions)
+    -> Option<~[BlockPos]>
+{
+    let mut open = OpenList::new();
+    open.push(distance(start, goal), start);
+    let mut closed = HashSet::new();
+    let mut cost = HashMap::new();
+    let mut came_from = HashMap::new();
+    cost.insert(start, 0.0);
+
+    let mut iterations = 0;
+    while !open.is_empty() && iterations < options.max_iterations {
+        let pos = open.pop();
+
+        if pos == goal { return Some(trace_path(&came_from, start, goal)) }
+        // nodes whose cost improved are in the open list more than once
+        if !closed.insert(pos) { loop }
+        iterations += 1;
+
+        let pos_cost = *cost.get(&pos);
+        for moves(world, pos, options).each |&(next, step)| {
+            if closed.contains(&next) { loop }
+
+            let next_cost = pos_cost + step;
+            let better = match cost.find(&next) {
+                Some(&c) => next_cost < c,
+                None => true
+            };
+            if !better { loop }
+
+            cost.insert(next, next_cost);
+            came_from.insert(next, pos);
+            open.push(next_cost + distance(next, goal), next);
+        }
+    }
+
+    None
+}
+
+fn trace_path(came_from: &HashMap<BlockPos, BlockPos>, start: BlockPos, goal: BlockPos)
+    -> ~[BlockPos]
+{
+    let mut path = ~[];
+    let mut pos = goal;
+    while pos != start {
+        path.push(pos);
+        pos = *came_from.get(&pos);
+    }
+    vec::reversed(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use chunk;
+    use coords::BlockPos;
+    use pathfind::*;
+    use world;
+    use world::World;
+
+    // one chunk with a stone floor at y = 0 and the given blocks on it
+    fn world_with(blocks: &[(BlockPos, chunk::Block)]) -> World {
+        world::test_world(world::new_test_chunk(), blocks)
+    }
+
+    // the given blocks across the whole chunk at x = 5, splitting it in two
+    fn wall(ys: &[int], block: chunk::Block) -> ~[(BlockPos, chunk::Block)] {
+        let mut blocks = ~[];
+        for int::range(0, 16) |z| {
+            for ys.each |&y| { blocks.push((BlockPos::new(5, y, z), block)); }
+        }
+        blocks
+    }
+
+    fn path(world: &World, options: &PathOptions) -> Option<~[BlockPos]> {
+        find_path(world, BlockPos::new(2, 1, 8), BlockPos::new(8, 1, 8), options)
+    }
+
+    #[test]
+    fn steps_up_one_block_but_not_two() {
+        let options = PathOptions::new();
+
+        let low = world_with(wall([1], chunk::Stone));
+        match path(&low, &options) {
+            Some(p) => assert!(p.any(|pos| pos.x == 5 && pos.y == 2)),
+            None => fail!(~"no path over a one block step")
+        }
+
+        let high = world_with(wall([1, 2], chunk::Stone));
+        assert!(path(&high, &options).is_none());
+    }
+
+    #[test]
+    fn jumps_one_block_gaps_but_not_two() {
+        let options = PathOptions::new();
+
+        let narrow = world_with(wall([0], chunk::Air));
+        match path(&narrow, &options) {
+            Some(p) => assert!(p.all(|pos| pos.x != 5)),
+            None => fail!(~"no path over a one block gap")
+        }
+
+        let mut blocks = wall([0], chunk::Air);
+        for int::range(0, 16) |z| { blocks.push((BlockPos::new(6, 0, z), chunk::Air)); }
+        let wide = world_with(blocks);
+        assert!(path(&wide, &options).is_none());
+    }
+
+    #[test]
+    fn falls_no_further_than_max_fall() {
+        // a pillar four blocks high to get down from
+        let mut blocks = ~[];
+        for int::range(1, 5) |y| { blocks.push((BlockPos::new(4, y, 8), chunk::Stone)); }
+        let world = world_with(blocks);
+        let (start, goal) = (BlockPos::new(4, 5, 8), BlockPos::new(8, 1, 8));
+
+        let mut options = PathOptions::new();
+        options.max_fall = 3;
+        assert!(find_path(&world, start, goal, &options).is_none());
+        options.max_fall = 4;
+        assert!(find_path(&world, start, goal, &options).is_some());
+    }
+
+    #[test]
+    fn closed_doors_only_open_when_allowed() {
+        let mut options = PathOptions::new();
+
+        let mut blocks = wall([1, 2], chunk::Stone);
+        blocks.push((BlockPos::new(5, 1, 8), chunk::Door(false)));
+        blocks.push((BlockPos::new(5, 2, 8), chunk::Air));
+        let closed = world_with(blocks);
+        assert!(path(&closed, &options).is_none());
+
+        let mut blocks = wall([1, 2], chunk::Stone);
+        blocks.push((BlockPos::new(5, 1, 8), chunk::Door(true)));
+        blocks.push((BlockPos::new(5, 2, 8), chunk::Air));
+        let open = world_with(blocks);
+        assert!(path(&open, &options).is_some());
+
+        options.open_doors = true;
+        match path(&closed, &options) {
+            Some(p) => assert!(p.contains(&BlockPos::new(5, 1, 8))),
+            None => fail!(~"no path through a door that may be opened")
+        }
+    }
+}
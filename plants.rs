@@ -0,0 +1,71 @@
+use chunk;
+use world::World;
+use coords::BlockPos;
+use core::rand::RngUtil;
+
+// chance out of 3 that a crop advances a stage on a random tick
+static CROP_GROWTH_CHANCE: uint = 1;
+static CROP_STAGES: u8 = 8;
+
+/* Grows or withers the block at pos. Plants only change blocks that were just read, so these
+ * edits shouldn't fail; if one does the plant simply stays as it was.
+ */
+fn set(world: &mut World, pos: BlockPos, b: chunk::Block) {
+    match world.replace_block(pos, b) {
+        Ok(()) => (),
+        Err(e) => println(fmt!("plant update at %?: %?", pos, e))
+    }
+}
+
+// a block is lit when nothing opaque sits directly on top of it
+pub fn is_lit(world: &World, pos: BlockPos) -> bool {
+    match world.block(pos.up()) {
+        Some(b) => !b.is_opaque(),
+        None => true
+    }
+}
+
+/* Grass dies when covered and otherwise spreads onto lit dirt nearby, up to three blocks
+ * down and one up.
+ */
+pub fn grass_tick(world: &mut World, pos: BlockPos) {
+    if !is_lit(world, pos) {
+        set(world, pos, chunk::Dirt);
+        return
+    }
+
+    let target = pos.offset(world.rng.gen_int_range(-1, 2),
+                            world.rng.gen_int_range(-3, 2),
+                            world.rng.gen_int_range(-1, 2));
+
+    match world.block(target) {
+        Some(chunk::Dirt) if is_lit(world, target) => set(world, target, chunk::Grass),
+        _ => ()
+    }
+}
+
+pub fn crop_tick(world: &mut World, pos: BlockPos) {
+    let stage = match world.block(pos) {
+        Some(chunk::Wheat(stage)) => stage,
+        _ => return
+    };
+
+    // crops need soil under them and light on top
+    match world.block(pos.down()) {
+        Some(chunk::Dirt) | Some(chunk::Grass) => (),
+        // popping off like when the block below is broken, seed and all
+        _ => {
+            match world.break_block(pos, None) {
+                Ok(()) => (),
+                Err(e) => println(fmt!("plant update at %?: %?", pos, e))
+            }
+            return
+        }
+    }
+
+    if stage + 1 < CROP_STAGES && is_lit(world, pos) &&
+       world.rng.gen_uint_range(0, 3) < CROP_GROWTH_CHANCE
+    {
+        set(world, pos, chunk::Wheat(stage + 1));
+    }
+}
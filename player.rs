@@ -0,0 +1,529 @@
+use world;
+use world::World;
+use aabb;
+use aabb::Aabb;
+use config::Config;
+use item::Inventory;
+use hunger::{Hunger, HungerConfig};
+
+use common::*;
+use coords::BlockPos;
+use core::float;
+
+// default speeds, see Speeds
+static MOVE_SPEED: float = 5.0f;
+static SPRINT_MULTIPLIER: float = 1.3f;
+static SNEAK_MULTIPLIER: float = 0.3f;
+static FLY_SPEED: float = 10.0f;
+static SPECTATOR_SPEED: float = 15.0f;
+// pressing jump twice within this many ticks toggles flying
+static DOUBLE_TAP_TICKS: uint = 6;
+// obstacles up to this high are walked onto without jumping
+static STEP_HEIGHT: float = 0.5f;
+// how far horizontal movement is backed off at a time when sneaking near a ledge
+static EDGE_STEP: float = 0.05f;
+// upward force of fluids, cancelling most of the gravity
+static BUOYANCY: float = 24.0f;
+static SWIM_SPEED: float = 3.0f;
+static JUMP_SPEED: float = 8.0f;
+pub static PLAYER_WIDTH: float = 0.6f;
+pub static PLAYER_HEIGHT: float = 1.8f;
+pub static EYE_HEIGHT: float = 1.62f;
+pub static MAX_HEALTH: int = 20;
+// ticks the player can stay under water before drowning
+pub static MAX_AIR: int = 300;
+// ticks between damage while drowning
+static DROWNING_INTERVAL: int = 20;
+static DROWNING_DAMAGE: int = 2;
+// falls up to this high don't hurt; every block further costs one health
+static SAFE_FALL_HEIGHT: float = 3.0f;
+// ticks the HUD tells the player they died for after respawning
+static DEATH_MESSAGE_TICKS: uint = 60;
+
+// The controls held down during a tick; filled in by the window code
+pub struct InputState {
+    forward: bool,
+    back: bool,
+    left: bool,
+    right: bool,
+    jump: bool,
+    sneak: bool,
+    sprint: bool
+}
+
+pub impl InputState {
+    fn new() -> InputState {
+        InputState { forward: false, back: false, left: false, right: false, jump: false,
+                     sneak: false, sprint: false }
+    }
+}
+
+pub enum GameMode {
+    // health, tools and limited blocks
+    Survival,
+    // no damage, instant breaking, unlimited blocks and flying
+    Creative
+}
+
+pub enum MoveMode {
+    Walking,
+    // no gravity, jump and sneak move up and down
+    Flying,
+    // like flying, but passing through blocks
+    Spectator
+}
+
+// Movement speeds in blocks per second, loaded from the config file
+pub struct Speeds {
+    walk: float,
+    sprint_multiplier: float,
+    sneak_multiplier: float,
+    fly: float,
+    spectator: float
+}
+
+pub impl Speeds {
+    fn from_config(config: &Config) -> Speeds {
+        Speeds {
+            walk: config.get_float("walk_speed", MOVE_SPEED),
+            sprint_multiplier: config.get_float("sprint_multiplier", SPRINT_MULTIPLIER),
+            sneak_multiplier: config.get_float("sneak_multiplier", SNEAK_MULTIPLIER),
+            fly: config.get_float("fly_speed", FLY_SPEED),
+            spectator: config.get_float("spectator_speed", SPECTATOR_SPEED)
+        }
+    }
+}
+
+pub struct Player {
+    position: Vec3f,
+    // position before the last tick, for interpolating between ticks when rendering
+    prev_position: Vec3f,
+    rot_x: float,
+    rot_y: float,
+    vel_y: float,
+    on_ground: bool,
+    mode: MoveMode,
+    speeds: Speeds,
+    // for detecting double taps of jump
+    jump_held: bool,
+    ticks_since_jump: uint,
+    mining_target: Option<(BlockPos, float)>,
+    inventory: Inventory,
+    game_mode: GameMode,
+    health: int,
+    // ticks of breath left; below zero while drowning
+    air: int,
+    hunger: Hunger,
+    // ticks left of showing that the player died
+    death_message: uint
+}
+
+pub impl Player {
+    fn new(position: Vec3f, speeds: Speeds, hunger: HungerConfig) -> Player {
+        Player {
+            position: position,
+            prev_position: position,
+            rot_x: 0.0, rot_y: 0.0,
+            vel_y: 0.0,
+            on_ground: false,
+            mode: Walking,
+            speeds: speeds,
+            jump_held: false,
+            ticks_since_jump: DOUBLE_TAP_TICKS,
+            mining_target: None,
+            inventory: Inventory::new(),
+            game_mode: Survival,
+            health: MAX_HEALTH,
+            air: MAX_AIR,
+            hunger: Hunger::new(hunger),
+            death_message: 0
+        }
+    }
+
+    fn is_creative(&self) -> bool {
+        match self.game_mode {
+            Creative => true,
+            Survival => false
+        }
+    }
+
+    // switches between survival and creative, landing the player when leaving creative
+    fn toggle_game_mode(&mut self) {
+        self.game_mode = match self.game_mode {
+            Survival => Creative,
+            Creative => {
+                self.mode = Walking;
+                Survival
+            }
+        };
+    }
+
+    fn damage(&mut self, amount: int) {
+        if !self.is_creative() {
+            self.health = int::max(self.health - amount, 0);
+        }
+    }
+
+    // hunger only drains in survival
+    fn exhaust(&mut self, amount: float) {
+        if !self.is_creative() {
+            self.hunger.exhaust(amount);
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.health <= 0
+    }
+
+    // brings the player back to life at the position, with the inventory left as it is
+    fn respawn(&mut self, at: Vec3f) {
+        self.position = at;
+        self.prev_position = at;
+        self.vel_y = 0.0;
+        self.on_ground = false;
+        self.health = MAX_HEALTH;
+        self.air = MAX_AIR;
+        self.hunger.reset();
+        self.mining_target = None;
+        self.death_message = DEATH_MESSAGE_TICKS;
+    }
+
+    fn aabb(&self) -> Aabb {
+        Aabb::from_feet(&self.position, PLAYER_WIDTH, PLAYER_HEIGHT)
+    }
+
+    fn eye_position(&self) -> Vec3f {
+        self.position.add_v(&Vec3f::new(0.0, EYE_HEIGHT, 0.0))
+    }
+
+    // eye position alpha of the way from the previous tick to the current one
+    fn interpolated_eye_position(&self, alpha: float) -> Vec3f {
+        let d = self.position.sub_v(&self.prev_position);
+        self.prev_position.add_v(&d.mul_t(alpha)).add_v(&Vec3f::new(0.0, EYE_HEIGHT, 0.0))
+    }
+
+    // switches between spectator mode and walking, in creative mode only
+    fn toggle_spectator(&mut self) {
+        if !self.is_creative() { return }
+        self.mode = match self.mode {
+            Spectator => Walking,
+            _ => Spectator
+        };
+        self.vel_y = 0.0;
+        self.on_ground = false;
+    }
+
+    // Advances the player by one world tick
+    fn tick(&mut self, world: &World, input: &InputState) {
+        let dt = 1.0 / world::TICKS_PER_SECOND;
+        self.prev_position = self.position;
+        if self.death_message > 0 { self.death_message -= 1; }
+
+        if input.jump && !self.jump_held {
+            if self.ticks_since_jump < DOUBLE_TAP_TICKS && self.is_creative() {
+                self.mode = match self.mode {
+                    Walking => Flying,
+                    Flying => Walking,
+                    Spectator => Spectator
+                };
+                self.vel_y = 0.0;
+                // a third tap starts a new double tap
+                self.ticks_since_jump = DOUBLE_TAP_TICKS;
+            } else {
+                self.ticks_since_jump = 0;
+            }
+        } else if self.ticks_since_jump < DOUBLE_TAP_TICKS {
+            self.ticks_since_jump += 1;
+        }
+        self.jump_held = input.jump;
+
+        let mode = self.mode;
+        match mode {
+            Spectator => (),
+            _ => self.breathe(world)
+        }
+        if !self.is_creative() {
+            let health = self.health;
+            let change = self.hunger.tick(health, MAX_HEALTH);
+            if change < 0 { self.damage(-change); }
+            else { self.health = int::min(self.health + change, MAX_HEALTH); }
+        }
+
+        match mode {
+            Walking => self.walk(world, input, dt),
+            Flying => {
+                let mut delta = self.horizontal_movement(input, self.speeds.fly * dt);
+                delta.y = vertical_input(input) * self.speeds.fly * dt;
+                let (moved, (_, hit_y, _)) = aabb::move_box(world, &self.aabb(), &delta);
+                self.position.add_self_v(&moved);
+
+                // flying down onto the ground lands
+                self.on_ground = hit_y && delta.y < 0.0;
+                if self.on_ground { self.mode = Walking; }
+            },
+            Spectator => {
+                let mut delta = self.horizontal_movement(input, self.speeds.spectator * dt);
+                delta.y = vertical_input(input) * self.speeds.spectator * dt;
+                self.position.add_self_v(&delta);
+            }
+        }
+    }
+
+    // movement along the ground plane for the held direction keys
+    fn horizontal_movement(&self, input: &InputState, distance: float) -> Vec3f {
+        let rot_hori = Quatf::from_angle_axis(self.rot_x, &Vec3f::new(0.0, 1.0, 0.0));
+        let plane_fwd = rot_hori.mul_v(&Vec3f::new(0.0, 0.0, -1.0));
+        let rt = rot_hori.mul_v(&Vec3f::new(1.0, 0.0, 0.0));
+
+        let mut target_pos = Vec3f::zero();
+        if input.left { target_pos.add_self_v(&rt.mul_t(-distance)); }
+        if input.right { target_pos.add_self_v(&rt.mul_t(distance)); }
+        if input.forward { target_pos.add_self_v(&plane_fwd.mul_t(distance)); }
+        if input.back { target_pos.add_self_v(&plane_fwd.mul_t(-distance)); }
+        target_pos
+    }
+
+    fn walk(&mut self, world: &World, input: &InputState, dt: float) {
+        let sprinting = input.sprint && !input.sneak && self.hunger.can_sprint();
+        let speed = if input.sneak {
+            self.speeds.walk * self.speeds.sneak_multiplier
+        } else if sprinting {
+            self.speeds.walk * self.speeds.sprint_multiplier
+        } else {
+            self.speeds.walk
+        };
+        let mut target_pos = self.horizontal_movement(input, speed * dt);
+
+        let in_fluid = match world.block_at_vec(&self.position.add_v(&Vec3f::new(0.0, 0.5, 0.0))) {
+            Some(b) => b.is_fluid(),
+            None => false
+        };
+
+        if in_fluid {
+            self.vel_y -= (world::GRAVITY - BUOYANCY) * dt;
+            self.vel_y *= float::max(1.0 - 2.0 * dt, 0.0);
+            target_pos = target_pos.mul_t(0.5);
+        } else {
+            self.vel_y -= world::GRAVITY * dt;
+        }
+
+        if input.jump {
+            if in_fluid {
+                self.vel_y = float::min(self.vel_y + 20.0 * dt, SWIM_SPEED);
+            } else if self.on_ground {
+                self.vel_y = JUMP_SPEED;
+                let exhaustion = self.hunger.config.jump_exhaustion;
+                self.exhaust(exhaustion);
+            }
+        }
+
+        if input.sneak && self.on_ground && !in_fluid {
+            target_pos = self.keep_on_edge(world, &target_pos);
+        }
+
+        let delta = Vec3f::new(target_pos.x, self.vel_y * dt, target_pos.z);
+        let (mut moved, (hit_x, mut hit_y, hit_z)) =
+            aabb::move_box(world, &self.aabb(), &delta);
+
+        if (hit_x || hit_z) && self.on_ground && delta.y <= 0.0 {
+            let stepped = self.step_up(world, &delta);
+            if horizontal_length(&stepped) > horizontal_length(&moved) {
+                moved = stepped;
+                // the step ends standing on the obstacle
+                hit_y = true;
+            }
+        }
+
+        self.position.add_self_v(&moved);
+        if sprinting {
+            let exhaustion = self.hunger.config.sprint_exhaustion * horizontal_length(&moved);
+            self.exhaust(exhaustion);
+        }
+
+        // landing or bumping the head both stop vertical movement
+        self.on_ground = hit_y && delta.y < 0.0;
+        if self.on_ground && !in_fluid {
+            self.land(-self.vel_y);
+        }
+        if hit_y { self.vel_y = 0.0; }
+    }
+
+    // takes fall damage for hitting the ground at the speed
+    fn land(&mut self, speed: float) {
+        let height = speed * speed / (2.0 * world::GRAVITY);
+        if height > SAFE_FALL_HEIGHT {
+            self.damage(float::ceil(height - SAFE_FALL_HEIGHT) as int);
+        }
+    }
+
+    // uses up air while the player's head is in a fluid, and drowns them once it's gone
+    fn breathe(&mut self, world: &World) {
+        let submerged = match world.block_at_vec(&self.eye_position()) {
+            Some(b) => b.is_fluid(),
+            None => false
+        };
+
+        if !submerged {
+            self.air = MAX_AIR;
+            return
+        }
+
+        self.air -= 1;
+        if self.air <= -DROWNING_INTERVAL {
+            self.air = 0;
+            self.damage(DROWNING_DAMAGE);
+        }
+    }
+
+    /* Retries a blocked horizontal movement from STEP_HEIGHT higher up, and then moves back
+     * down onto whatever was stepped on
+     */
+    fn step_up(&self, world: &World, delta: &Vec3f) -> Vec3f {
+        let start = self.aabb();
+        let (up, _) = aabb::move_box(world, &start, &Vec3f::new(0.0, STEP_HEIGHT, 0.0));
+        let raised = start.offset(&up);
+        let (side, _) = aabb::move_box(world, &raised, &Vec3f::new(delta.x, 0.0, delta.z));
+        let (down, _) = aabb::move_box(world, &raised.offset(&side),
+                                       &Vec3f::new(0.0, -up.y, 0.0));
+        up.add_v(&side).add_v(&down)
+    }
+
+    /* Shortens horizontal movement so that the player doesn't end up further than a step
+     * above the ground, one axis at a time like Aabb::clip
+     */
+    fn keep_on_edge(&self, world: &World, target: &Vec3f) -> Vec3f {
+        // the space a step deep under the player's feet, so that walls beside them don't count
+        let b = self.aabb();
+        let below = Aabb::new(Vec3f::new(b.min.x, b.min.y - STEP_HEIGHT, b.min.z),
+                              Vec3f::new(b.max.x, b.min.y, b.max.z));
+        let supported = |dx: float, dz: float| {
+            aabb::collides(world, &below.offset(&Vec3f::new(dx, 0.0, dz)))
+        };
+        let back_off = |d: float| {
+            if float::abs(d) < EDGE_STEP { 0.0 }
+            else if d > 0.0 { d - EDGE_STEP }
+            else { d + EDGE_STEP }
+        };
+
+        let (mut dx, mut dz) = (target.x, target.z);
+        while dx != 0.0 && !supported(dx, 0.0) { dx = back_off(dx); }
+        while dz != 0.0 && !supported(0.0, dz) { dz = back_off(dz); }
+        while dx != 0.0 && dz != 0.0 && !supported(dx, dz) {
+            dx = back_off(dx);
+            dz = back_off(dz);
+        }
+        Vec3f::new(dx, target.y, dz)
+    }
+}
+
+// 1 for up, -1 for down when flying
+fn vertical_input(input: &InputState) -> float {
+    (if input.jump { 1.0 } else { 0.0 }) - (if input.sneak { 1.0 } else { 0.0 })
+}
+
+fn horizontal_length(v: &Vec3f) -> float {
+    float::sqrt(v.x * v.x + v.z * v.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use chunk;
+    use config::Config;
+    use coords::BlockPos;
+    use hunger::HungerConfig;
+    use player::*;
+    use world;
+    use world::World;
+
+    use common::*;
+    use core::float;
+
+    // one chunk with a stone floor at y = 0
+    fn floor_world() -> World {
+        world::test_world(world::new_test_chunk(), [])
+    }
+
+    fn player_at(x: float, y: float, z: float) -> Player {
+        let config = Config::new();
+        Player::new(Vec3f::new(x, y, z), Speeds::from_config(&config),
+                    HungerConfig::from_config(&config))
+    }
+
+    fn close(a: float, b: float) -> bool {
+        float::abs(a - b) < 1e-6
+    }
+
+    #[test]
+    fn gravity_accelerates_a_falling_player() {
+        let world = floor_world();
+        let mut p = player_at(8.5, 10.0, 8.5);
+
+        p.tick(&world, &InputState::new());
+        let (y, vel_y) = (p.position.y, p.vel_y);
+        assert!(vel_y < 0.0 && y < 10.0 && !p.on_ground);
+
+        p.tick(&world, &InputState::new());
+        assert!(p.vel_y < vel_y);
+        assert!(y - p.position.y > 10.0 - y);
+    }
+
+    #[test]
+    fn lands_on_the_floor() {
+        let world = floor_world();
+        let mut p = player_at(8.5, 3.0, 8.5);
+        for 40.times { p.tick(&world, &InputState::new()); }
+
+        assert!(p.on_ground);
+        assert!(close(p.position.y, 1.0));
+        assert_eq!(p.vel_y, 0.0);
+        // two blocks is a safe fall
+        assert_eq!(p.health, MAX_HEALTH);
+    }
+
+    #[test]
+    fn long_falls_hurt() {
+        let world = floor_world();
+        let mut p = player_at(8.5, 12.0, 8.5);
+        for 40.times { p.tick(&world, &InputState::new()); }
+
+        assert!(p.on_ground);
+        assert!(p.health < MAX_HEALTH);
+    }
+
+    #[test]
+    fn blocks_over_a_drop_are_not_ground() {
+        let mut world = floor_world();
+        // the floor ends at z = 4, with a block at head height hanging over the drop
+        for int::range(0, 16) |x| {
+            for int::range(0, 4) |z| {
+                assert!(world.replace_block(BlockPos::new(x, 0, z), chunk::Air).is_ok());
+            }
+        }
+        assert!(world.replace_block(BlockPos::new(8, 2, 3), chunk::Stone).is_ok());
+
+        let p = player_at(8.5, 1.0, 4.0 + PLAYER_WIDTH / 2.0);
+        let kept = p.keep_on_edge(&world, &Vec3f::new(0.0, 0.0, -1.0));
+        // backed off until part of the player is still over the floor
+        assert!(kept.z > -PLAYER_WIDTH - 1e-6 && kept.z < -PLAYER_WIDTH + 0.1);
+        assert_eq!(kept.x, 0.0);
+    }
+
+    #[test]
+    fn walls_stop_walking() {
+        let mut world = floor_world();
+        for int::range(0, 16) |x| {
+            for int::range(1, 3) |y| {
+                assert!(world.replace_block(BlockPos::new(x, y, 5), chunk::Stone).is_ok());
+            }
+        }
+        let mut p = player_at(8.5, 1.0, 8.5);
+        let mut input = InputState::new();
+        // facing -z
+        input.forward = true;
+        for 40.times { p.tick(&world, &input); }
+
+        assert!(close(p.position.z, 6.0 + PLAYER_WIDTH / 2.0));
+        assert!(close(p.position.x, 8.5));
+        assert!(close(p.position.y, 1.0));
+    }
+}
@@ -0,0 +1,40 @@
+use chunk;
+use item;
+use item::{Item, BlockItem};
+
+use core::hashmap::HashMap;
+
+// Looks up items by the ids used in data files, so that those don't depend on the Block enum
+pub struct Registry {
+    items: HashMap<~str, Item>
+}
+
+pub impl Registry {
+    // registers every block and item under its id
+    fn new() -> Registry {
+        let mut registry = Registry { items: HashMap::new() };
+
+        for uint::range(1, 256) |id| {
+            match chunk::Block::from_id(id as u8, 0) {
+                Some(block) => registry.register(BlockItem(block)),
+                None => ()
+            }
+        }
+        for item::plain_items.each |&i| {
+            registry.register(i);
+        }
+
+        registry
+    }
+
+    fn register(&mut self, item: Item) {
+        self.items.insert(item.id(), item);
+    }
+
+    fn find(&self, id: &str) -> Option<Item> {
+        match self.items.find(&id.to_owned()) {
+            Some(&item) => Some(item),
+            None => None
+        }
+    }
+}
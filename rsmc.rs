@@ -9,9 +9,27 @@ pub mod shader;
 pub mod buffer;
 pub mod texture;
 pub mod font;
+pub mod hud;
+pub mod sky;
 pub mod common;
+pub mod coords;
+pub mod config;
+pub mod aabb;
 
 pub mod chunk;
 pub mod world;
+pub mod worldgen;
+pub mod fluid;
+pub mod plants;
+pub mod entity;
+pub mod pathfind;
+pub mod mob;
+pub mod light;
+pub mod daytime;
+pub mod player;
+pub mod hunger;
+pub mod item;
+pub mod registry;
+pub mod crafting;
 
 pub mod noise;
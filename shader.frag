@@ -2,6 +2,9 @@
 #extension GL_EXT_texture_array : enable
 layout (location = 0) out vec4 outputColor;
 uniform sampler2DArray texture;
+uniform vec3 sun_colour;
+// 0 at night, 1 in full daylight
+uniform float daylight;
 
 in vec3 v_texcoord;
 in vec3 v_position;
@@ -14,8 +17,8 @@ void main() {
 
     vec4 n_eye = normalize(vneye);
 
-    vec4 Ia = vec4(0.13, 0.13, 0.13, 1.0);
-    vec4 Id = vec4(0.75, 0.75, 0.75, 1.0) * max(dot(lieye, n_eye), 0.0);
+    vec4 Ia = vec4(vec3(0.06 + 0.07 * daylight), 1.0);
+    vec4 Id = vec4(0.75 * sun_colour, 1.0) * max(dot(lieye, n_eye), 0.0);
     outputColor = Ld * (Ia + Id);
 
     if (gl_FragCoord.x > 638 && gl_FragCoord.x < 642 && gl_FragCoord.y > 398 && gl_FragCoord.y < 402)
@@ -94,6 +94,19 @@ pub impl Program {
         glProgramUniform1i(self.handle, self.uniform_location(uniform) as i32, value as i32);
     }
 
+    fn set_uniform_vec3(&self, uniform: &str, v: &Vec3f) {
+        self.bind();
+
+        glProgramUniform3f(self.handle, self.uniform_location(uniform) as i32,
+                           v.x as f32, v.y as f32, v.z as f32);
+    }
+
+    fn set_uniform_float(&self, uniform: &str, value: float) {
+        self.bind();
+
+        glProgramUniform1f(self.handle, self.uniform_location(uniform) as i32, value as f32);
+    }
+
     fn bind(&self) {
         glUseProgram(self.handle);
     }
@@ -4,6 +4,8 @@ in vec3 texcoord;
 in vec3 normal;
 uniform mat4 projection;
 uniform mat4 modelview;
+// points towards the sun, or the moon at night
+uniform vec3 sun_direction;
 
 out vec3 v_texcoord;
 out vec3 v_position;
@@ -16,6 +18,6 @@ void main() {
     v_texcoord = texcoord;
     v_position = position;
 
-    lieye = modelview * vec4(0.0, 1.0, 0.0, 0.0);
+    lieye = modelview * vec4(sun_direction, 0.0);
     vneye = modelview * vec4(normal, 0.0);
 }
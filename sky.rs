@@ -0,0 +1,263 @@
+use daytime;
+use noise::Noise2DContext;
+use shader::Program;
+use buffer::Buffer;
+
+use glcore::*;
+use common::*;
+use core::float;
+use core::rand;
+use core::rand::RngUtil;
+use numeric::*;
+
+// everything in the sky is drawn inside this distance, short of the far plane
+static SKY_RADIUS: float = 50.0;
+static DOME_RINGS: uint = 8;
+static DOME_SEGMENTS: uint = 16;
+// the dome reaches a little below the horizon, in radians
+static DOME_SKIRT: float = 0.2;
+static SUN_SIZE: float = 4.0;
+static MOON_SIZE: float = 3.0;
+static STAR_COUNT: uint = 500;
+// world height of the cloud layer and the size of its cells; the layer is drawn no higher
+// than half of SKY_RADIUS above the camera, to keep it inside the sky
+static CLOUD_HEIGHT: float = 72.0;
+static CLOUD_CELL: float = 6.0;
+// blocks the clouds drift along x every tick
+static CLOUD_SPEED: float = 0.02;
+static CLOUD_NOISE_SCALE: f32 = 0.3;
+// noise above this is a cloud
+static CLOUD_COVER: f32 = 0.1;
+static CLOUD_OPACITY: float = 0.8;
+// the same clouds in every world
+static CLOUD_SEED: uint = 5535;
+
+// Vertices uploaded to the GPU along with how many there are
+struct Mesh {
+    buffer: Buffer,
+    len: uint
+}
+
+fn upload(vertices: &[Vec3f]) -> Mesh {
+    let mut buffer = Buffer::new();
+    buffer.update(vertices);
+    Mesh { buffer: buffer, len: vertices.len() }
+}
+
+/* The dome, sun, moon, stars and clouds drawn behind everything else. Their meshes are
+ * uploaded once, apart from the clouds which follow the camera, and their colours change
+ * through uniforms.
+ */
+pub struct Sky {
+    program: Program,
+    dome: Mesh,
+    // the stars, sun and moon as they are at sunrise; they turn along with the sun
+    stars: Mesh,
+    sun: Mesh,
+    moon: Mesh,
+    clouds: Noise2DContext,
+    // the cloud cells around the cell they were built for, kept until the camera or the
+    // drift moves on to another
+    cloud_mesh: Mesh,
+    cloud_cell: Option<(int, int)>
+}
+
+// a point on the dome at the elevation and azimuth, in radians
+fn dome_point(elevation: float, azimuth: float) -> Vec3f {
+    Vec3f::new(float::cos(elevation) * float::cos(azimuth), float::sin(elevation),
+               float::cos(elevation) * float::sin(azimuth)).mul_t(SKY_RADIUS)
+}
+
+// rings of quads from a little below the horizon up to the zenith
+fn dome_vertices() -> ~[Vec3f] {
+    let mut vertices = ~[];
+    let ring = (float::consts::pi / 2.0 + DOME_SKIRT) / DOME_RINGS as float;
+    let segment = 2.0 * float::consts::pi / DOME_SEGMENTS as float;
+    for uint::range(0, DOME_RINGS) |i| {
+        let (e0, e1) = (i as float * ring - DOME_SKIRT, (i + 1) as float * ring - DOME_SKIRT);
+        for uint::range(0, DOME_SEGMENTS) |j| {
+            let (a0, a1) = (j as float * segment, (j + 1) as float * segment);
+            vertices.push_all([dome_point(e0, a0), dome_point(e0, a1),
+                               dome_point(e1, a1), dome_point(e1, a0)]);
+        }
+    }
+    vertices
+}
+
+/* A square facing the origin, size across and centred distance along dir, which has to be
+ * a horizontal unit vector
+ */
+fn billboard(dir: &Vec3f, distance: float, size: float) -> ~[Vec3f] {
+    let c = dir.mul_t(distance);
+    let u = Vec3f::new(0.0, size / 2.0, 0.0);
+    let v = Vec3f::new(dir.z, 0.0, -dir.x).mul_t(size / 2.0);
+    ~[c.sub_v(&u).sub_v(&v), c.sub_v(&u).add_v(&v), c.add_v(&u).add_v(&v), c.add_v(&u).sub_v(&v)]
+}
+
+pub impl Sky {
+    fn new() -> Sky {
+        // the same stars every time
+        let r = rand::seeded_rng(&[7, 29, 3, 111]);
+        let mut stars = ~[];
+        while stars.len() < STAR_COUNT {
+            let v = Vec3f::new(r.gen_float() * 2.0 - 1.0, r.gen_float() * 2.0 - 1.0,
+                               r.gen_float() * 2.0 - 1.0);
+            // only points inside the ball, so that the directions are even
+            let length = v.length();
+            if length > 0.01 && length <= 1.0 { stars.push(v.mul_t(SKY_RADIUS / length)) }
+        }
+
+        let sun = Vec3f::new(1.0, 0.0, 0.3);
+        let sun = sun.div_t(sun.length());
+
+        Sky {
+            program: Program::new(vertex_shader, fragment_shader),
+            dome: upload(dome_vertices()),
+            stars: upload(stars),
+            sun: upload(billboard(&sun, SKY_RADIUS * 0.9, SUN_SIZE)),
+            moon: upload(billboard(&sun.mul_t(-1.0), SKY_RADIUS * 0.9, MOON_SIZE)),
+            clouds: Noise2DContext::new(CLOUD_SEED),
+            cloud_mesh: upload([]),
+            cloud_cell: None
+        }
+    }
+
+    /* Draws the sky around the camera, view being its rotation only. This comes right after
+     * clearing the screen: no depth is written, so that everything else is drawn over it.
+     */
+    fn draw(&mut self, projection: &Mat4f, view: &Mat4f, camera: &Vec3f, time: u64) {
+        let daylight = daytime::daylight(time);
+        let sky = daytime::sky_colour(time);
+        // the sun, moon and stars go around the z axis, see daytime::sun_direction
+        let turn = Quatf::from_angle_axis(daytime::sun_angle(time), &Vec3f::new(0.0, 0.0, 1.0));
+        let celestial = view.mul_m(&turn.to_mat3().to_mat4());
+
+        self.program.set_uniform_mat4("projection", projection);
+        glDepthMask(GL_FALSE);
+        glDisable(GL_CULL_FACE);
+        glEnable(GL_BLEND);
+        glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+
+        // fading from the horizon colour up to the zenith colour
+        let horizon = mix(&sky, &Vec3f::new(1.0, 1.0, 1.0), 0.05 + 0.25 * daylight);
+        self.program.set_uniform_mat4("modelview", view);
+        self.draw_shaded(GL_QUADS, &self.dome, &horizon, &sky.mul_t(0.7), 1.0);
+
+        self.program.set_uniform_mat4("modelview", &celestial);
+        if daylight < 1.0 {
+            glPointSize(2.0);
+            self.draw_mesh(GL_POINTS, &self.stars, &Vec3f::new(1.0, 1.0, 1.0), 1.0 - daylight);
+        }
+        self.draw_mesh(GL_QUADS, &self.sun, &Vec3f::new(1.0, 0.95, 0.7), 1.0);
+        // faint in daylight
+        self.draw_mesh(GL_QUADS, &self.moon, &Vec3f::new(0.85, 0.85, 0.95),
+                       1.0 - 0.6 * daylight);
+
+        self.draw_clouds(view, camera, time, daylight);
+
+        glEnable(GL_CULL_FACE);
+        glDepthMask(GL_TRUE);
+    }
+
+    // the cloud layer around the camera, drifting with time
+    fn draw_clouds(&mut self, view: &Mat4f, camera: &Vec3f, time: u64, daylight: float) {
+        let y = float::min(CLOUD_HEIGHT - camera.y, SKY_RADIUS * 0.5);
+        // this far below the camera the layer would be outside the sky
+        if y < -SKY_RADIUS * 0.5 { return }
+
+        let drift = time as float * CLOUD_SPEED;
+        let cell = (float::floor((camera.x + drift) / CLOUD_CELL) as int,
+                    float::floor(camera.z / CLOUD_CELL) as int);
+        if self.cloud_cell != Some(cell) {
+            self.build_clouds(cell);
+            self.cloud_cell = Some(cell);
+        }
+
+        // the cells are laid out in the clouds' own space, which drifts along x
+        let modelview = view.mul_m(&translation_matrix((-drift - camera.x, y, -camera.z)));
+        self.program.set_uniform_mat4("modelview", &modelview);
+        let colour = mix(&Vec3f::new(0.15, 0.15, 0.2), &Vec3f::new(1.0, 1.0, 1.0), daylight);
+        self.draw_mesh(GL_QUADS, &self.cloud_mesh, &colour, CLOUD_OPACITY);
+    }
+
+    // uploads the cloud cells close enough to the given one to be inside the sky
+    fn build_clouds(&mut self, cell: (int, int)) {
+        let (ci, cj) = cell;
+        // with the layer at most half of SKY_RADIUS above or below, less the cell the camera
+        // can be anywhere in
+        let reach = float::sqrt(0.75) * SKY_RADIUS - CLOUD_CELL;
+        let range = (reach / CLOUD_CELL) as int + 1;
+
+        let mut vertices = ~[];
+        for int::range(ci - range, ci + range + 1) |i| {
+            for int::range(cj - range, cj + range + 1) |j| {
+                let (di, dj) = ((i - ci) as float * CLOUD_CELL, (j - cj) as float * CLOUD_CELL);
+                if di * di + dj * dj > reach * reach { loop }
+
+                let cover = self.clouds.get(i as f32 * CLOUD_NOISE_SCALE,
+                                            j as f32 * CLOUD_NOISE_SCALE);
+                if cover < CLOUD_COVER { loop }
+
+                let (x, z) = (i as float * CLOUD_CELL, j as float * CLOUD_CELL);
+                vertices.push_all([Vec3f::new(x, 0.0, z), Vec3f::new(x + CLOUD_CELL, 0.0, z),
+                                   Vec3f::new(x + CLOUD_CELL, 0.0, z + CLOUD_CELL),
+                                   Vec3f::new(x, 0.0, z + CLOUD_CELL)]);
+            }
+        }
+
+        self.cloud_mesh.buffer.update(vertices);
+        self.cloud_mesh.len = vertices.len();
+    }
+
+    // draws the mesh all in one colour
+    fn draw_mesh(&self, mode: GLenum, mesh: &Mesh, colour: &Vec3f, opacity: float) {
+        self.draw_shaded(mode, mesh, colour, colour, opacity);
+    }
+
+    /* Draws the mesh shading from the horizon colour at and below the horizon to the zenith
+     * colour straight up
+     */
+    fn draw_shaded(&self, mode: GLenum, mesh: &Mesh, horizon: &Vec3f, zenith: &Vec3f,
+                   opacity: float) {
+        if mesh.len == 0 { return }
+
+        self.program.set_attribute_vec3("position", &mesh.buffer);
+        self.program.set_uniform_vec3("horizon", horizon);
+        self.program.set_uniform_vec3("zenith", zenith);
+        self.program.set_uniform_float("opacity", opacity);
+
+        glDrawArrays(mode, 0, mesh.len as i32);
+
+        // unbind so that we aren't bound to a deleted buffer
+        glBindBuffer(GL_ARRAY_BUFFER, 0);
+    }
+}
+
+static vertex_shader: &'static str = "
+#version 330
+in vec3 position;
+uniform mat4 projection;
+uniform mat4 modelview;
+
+// how far up the sky the vertex is, from 0 at the horizon to 1 straight up
+out float v_height;
+
+void main() {
+    gl_Position = projection * modelview * vec4(position, 1.0);
+    v_height = max(position.y / max(length(position), 0.001), 0.0);
+}
+";
+
+static fragment_shader: &'static str = "
+#version 330
+layout (location = 0) out vec4 outputColor;
+uniform vec3 horizon;
+uniform vec3 zenith;
+uniform float opacity;
+
+in float v_height;
+
+void main() {
+    outputColor = vec4(mix(horizon, zenith, v_height), opacity);
+}
+";
@@ -1,72 +1,119 @@
 use chunk;
 use chunk::Chunk;
-use noise::Noise2DContext;
+use worldgen;
+use fluid;
+use plants;
+use entity;
+use entity::{Entity, ItemDrop};
+use item;
+use item::{Item, ItemStack};
+use mob;
+use player::Player;
+use aabb::Aabb;
+use worldgen::Generator;
 use core::hashmap::HashMap;
 use common::*;
+use coords::*;
 use lmath::vec::*;
 use numeric::*;
 use core::float;
+use core::rand;
+use core::util;
+use core::rand::{Rng, RngUtil};
+
+pub static WORLD_SEED: uint = 1337;
+pub static TICKS_PER_SECOND: float = 20.0;
+// downward acceleration shared by the player and everything else that falls
+pub static GRAVITY: float = 30.0;
+// highest world y blocks can be placed at
+pub static BUILD_LIMIT: int = 63;
+// blocks per chunk picked for a random tick every tick
+static RANDOM_TICKS_PER_CHUNK: uint = 3;
+static SAVE_DIR: &'static str = "world";
+// holds what's saved about the world apart from its chunks
+static WORLD_FILE: &'static str = "world.dat";
+// neighbour updates handled per edit before the rest is left for the next tick
+static MAX_BLOCK_UPDATES: uint = 4096;
+// identical items closer than this join into one stack
+static ITEM_MERGE_DISTANCE: float = 0.75;
+// chunks kept loaded in each direction around the player's
+static LOAD_RADIUS: int = 1;
+// further out than this chunks are unloaded, leaving a margin so walking back and forth over
+// a chunk border doesn't keep unloading and reloading them
+static UNLOAD_RADIUS: int = 2;
 
 pub struct World {
-    loaded_chunks: HashMap<(int, int, int), Chunk>
+    loaded_chunks: HashMap<ChunkPos, Chunk>,
+    generator: Generator,
+    // feature blocks and queued edits waiting for their chunk to be loaded
+    pending_writes: HashMap<ChunkPos, ~[(LocalPos, chunk::Block, bool)]>,
+    ticks: u64,
+    // time of day, see daytime; unlike ticks it's saved with the world
+    time: u64,
+    rng: @Rng,
+    // blocks to notify that a neighbour of theirs changed
+    block_updates: ~[BlockPos],
+    updating_blocks: bool
+}
+
+#[deriving(Eq)]
+pub enum EditError {
+    // the chunk isn't loaded; see World::replace_block_queued
+    Unloaded,
+    // below the bedrock floor or above the build limit
+    OutOfWorld,
+    // the block there can't be changed
+    Protected
+}
+
+pub struct RayHit {
+    pos: BlockPos,
+    block: chunk::Block,
+    // where the ray entered the block
+    point: Vec3f,
+    // outward normal of the face that was hit, (0,0,0) if the ray started inside the block
+    normal: BlockPos,
+    distance: float
 }
 
-fn new_test_chunk() -> Chunk {
+pub fn new_test_chunk() -> Chunk {
     let mut c = Chunk::new();
     for c.each_block_mut |(_,y,_), block| {
         *block = if y == 0 { chunk::Stone } else { chunk::Air };
     };
-    c.update_buffer_cache();
     c
 }
 
-fn new_stair_chunk() -> Chunk {
+pub fn new_stair_chunk() -> Chunk {
     let mut c = Chunk::new();
     for c.each_block_mut |(x,y,_), block| {
         if 16-x == y { *block = chunk::Stone } else { *block = chunk::Air };
         if x == 0 { *block = chunk::Stone };
     };
-    c.update_buffer_cache();
     c
 }
 
-fn new_empty_chunk() -> Chunk {
+pub fn new_empty_chunk() -> Chunk {
     let mut c = Chunk::new();
     for c.each_block_mut |_, block| {
         *block = chunk::Air;
     };
-    c.update_buffer_cache();
     c
 }
 
-fn new_noise_chunk(x_offs: float, z_offs: float) -> Chunk {
-    let mut c = Chunk::new();
-    let ctx = Noise2DContext::new();
-    for c.each_block_mut |(x,y,z), block| {
-        let h = ctx.get(x_offs as f32 + x as f32 * 0.1, z_offs as f32 + z as f32 * 0.1);
-        *block = if y as f32 / 6.0 < h {
-            if y < 5 { chunk::Grass } else { chunk::Stone }
-        } else { chunk::Air };
-        if y == 0 { *block = chunk::Dirt }
-    };
-    c.update_buffer_cache();
-    c
-}
-
-fn rem(a: int, b: int) -> int {
-    if a >= 0 {
-        a % b
-    } else {
-        b - (-a) % b
-    }
-}
-
-fn div(a: int, b: int) -> int {
-    if a >= 0 {
-        a / b
-    } else {
-        a / b - 1
+/* A world of just the chunk at the origin, with the blocks set straight into it without any
+ * block updates. The unloaded chunks around it are solid and can't be walked through. For
+ * tests, which need no save directory or GL context.
+ */
+#[cfg(test)]
+pub fn test_world(c: Chunk, blocks: &[(BlockPos, chunk::Block)]) -> World {
+    let mut c = c;
+    for blocks.each |&(pos, block)| {
+        *c.block_at_mut(pos.local()).unwrap() = block;
     }
+    let mut w = World::empty();
+    w.set_chunk(ChunkPos::new(0, 0, 0), c);
+    w
 }
 
 fn sgn(x: float) -> int {
@@ -74,125 +121,923 @@ fn sgn(x: float) -> int {
 }
 
 pub impl World {
+    // the saved world, with the chunks around the origin loaded
     fn new() -> World {
-        let mut w = World {
-            loaded_chunks: HashMap::new()
-        };
-        w.loaded_chunks.insert(( 0, 0, 0), new_empty_chunk());
-        w.loaded_chunks.insert(( 0,-1, 0), new_noise_chunk( 0.0, 0.0));
-        w.loaded_chunks.insert(( 1,-1, 0), new_noise_chunk( 1.6, 0.0));
-        w.loaded_chunks.insert((-1,-1, 0), new_noise_chunk(-1.6, 0.0));
-        w.loaded_chunks.insert(( 0,-1, 1), new_noise_chunk( 0.0, 1.6));
-        w.loaded_chunks.insert(( 0,-1,-1), new_noise_chunk( 0.0,-1.6));
-        w.loaded_chunks.insert(( 1,-1, 1), new_noise_chunk( 1.6, 1.6));
-        w.loaded_chunks.insert((-1,-1, 1), new_noise_chunk(-1.6, 1.6));
-        w.loaded_chunks.insert(( 1,-1,-1), new_noise_chunk( 1.6,-1.6));
-        w.loaded_chunks.insert((-1,-1,-1), new_noise_chunk(-1.6,-1.6));
-/*        w.loaded_chunks.insert((2,-1, 0), new_test_chunk());
-        w.loaded_chunks.insert((1,-1, 0), new_stair_chunk());
-        w.loaded_chunks.insert((1, 0, 0), new_empty_chunk());
-        w.loaded_chunks.insert((2, 0, 0), new_empty_chunk());*/
+        let mut w = World::empty();
+        w.read_world_file();
+        for int::range(-1, 2) |x| {
+            for int::range(-1, 2) |z| {
+                w.load_chunk(ChunkPos::new(x, -1, z));
+                w.load_chunk(ChunkPos::new(x,  0, z));
+            }
+        }
+/*        w.set_chunk(ChunkPos::new(2,-1, 0), new_test_chunk());
+        w.set_chunk(ChunkPos::new(1,-1, 0), new_stair_chunk());
+        w.set_chunk(ChunkPos::new(1, 0, 0), new_empty_chunk());
+        w.set_chunk(ChunkPos::new(2, 0, 0), new_empty_chunk());*/
         w
     }
 
-    fn block_at_vec(&self, pos: &Vec3f) -> Option<&'self chunk::Block> {
-        let cc = (pos.x,pos.y,pos.z).floor();
+    /* A world with nothing loaded that never reads or writes the save directory and needs no
+     * GL context, for tests
+     */
+    fn empty() -> World {
+        World {
+            loaded_chunks: HashMap::new(),
+            generator: Generator::new(WORLD_SEED),
+            pending_writes: HashMap::new(),
+            ticks: 0,
+            time: 0,
+            rng: rand::Rng(),
+            block_updates: ~[],
+            updating_blocks: false
+        }
+    }
+
+    // puts the chunk in place of whatever was loaded there, without generating or saving
+    fn set_chunk(&mut self, cc: ChunkPos, c: Chunk) {
+        self.loaded_chunks.insert(cc, c);
+    }
+
+    fn load_chunk(&mut self, cc: ChunkPos) {
+        let saved = self.read_saved_chunk(cc);
+        let generated = saved.is_none();
+        let mut c = match saved {
+            Some(c) => c,
+            None => self.generator.generate(cc)
+        };
+        let queued = match self.pending_writes.pop(&cc) {
+            Some(writes) => {
+                for writes.each |&(pos, block, force)| {
+                    worldgen::apply_write(&mut c, pos, block, force);
+                }
+                writes
+            },
+            None => ~[]
+        };
+
+        // features of saved chunks have already been written into their neighbours
+        let writes = if generated { self.generator.features(&c, cc) } else { ~[] };
+        self.loaded_chunks.insert(cc, c);
+
+        let mut touched = ~[cc];
+        for writes.each |w| {
+            let (target, local) = (w.pos.chunk(), w.pos.local());
+
+            if self.loaded_chunks.contains_key(&target) {
+                worldgen::apply_write(self.loaded_chunks.find_mut(&target).unwrap(), local,
+                                      w.block, w.force);
+                if !touched.contains(&target) { touched.push(target) }
+            } else {
+                self.queue_write(target, local, w.block, w.force);
+            }
+        }
+
+        for touched.each |cc| {
+            self.loaded_chunks.find_mut(cc).unwrap().dirty = true;
+        }
+
+        // edits made while the chunk was away get the neighbour updates they missed
+        for queued.each |&(pos, _, _)| {
+            let pos = cc.block(pos);
+            self.block_updates.push(pos);
+            for pos.each_neighbour |n| {
+                self.block_updates.push(n);
+            }
+        }
+        if !queued.is_empty() && !self.updating_blocks {
+            self.process_block_updates();
+        }
+    }
+
+    // where players start and respawn: above the terrain at the middle of the origin chunk
+    fn spawn_point(&self) -> Vec3f {
+        let (x, z) = (8, 8);
+        let mut pos = BlockPos::new(x, self.generator.surface_height(x, z) + 1, z);
+        let free = |p: BlockPos| match self.block_at(p) {
+            Some(b) => !b.blocks() && !b.is_fluid(),
+            None => true
+        };
+        while pos.y < BUILD_LIMIT && !(free(pos) && free(pos.up())) {
+            pos = pos.up();
+        }
+        Vec3f::new(x as float + 0.5, pos.y as float, z as float + 0.5)
+    }
+
+    fn chunk_path(&self, cc: ChunkPos) -> Path {
+        path::Path(fmt!("%s/c.%d.%d.%d.dat", SAVE_DIR, cc.x, cc.y, cc.z))
+    }
+
+    fn read_saved_chunk(&self, cc: ChunkPos) -> Option<Chunk> {
+        let path = self.chunk_path(cc);
+        if !os::path_exists(&path) { return None }
+
+        match io::file_reader(&path) {
+            Ok(r) => Chunk::read(r, self.ticks),
+            Err(e) => {
+                println(fmt!("could not load chunk %?: %s", cc, e));
+                None
+            }
+        }
+    }
+
+    fn save_chunk(&self, cc: ChunkPos) {
+        os::make_dir(&path::Path(SAVE_DIR), 493); // rwxr-xr-x
+        match io::file_writer(&self.chunk_path(cc), [io::Create, io::Truncate]) {
+            Ok(w) => self.loaded_chunks.get(&cc).write(w, self.ticks),
+            Err(e) => println(fmt!("could not save chunk %?: %s", cc, e))
+        }
+    }
+
+    fn world_file_path(&self) -> Path {
+        path::Path(fmt!("%s/%s", SAVE_DIR, WORLD_FILE))
+    }
+
+    fn read_world_file(&mut self) {
+        let path = self.world_file_path();
+        if !os::path_exists(&path) { return }
+
+        match io::file_reader(&path) {
+            Ok(r) => self.read_world(r),
+            Err(e) => println(fmt!("could not load world: %s", e))
+        }
+    }
+
+    /* The time, followed by the writes still waiting for chunks that were never loaded, so
+     * that features reaching into them aren't lost
+     */
+    fn read_world(&mut self, r: @io::Reader) {
+        self.time = r.read_be_u64();
+
+        // world files saved before pending writes were stored end here
+        if r.eof() { return }
+        for (r.read_be_u32() as uint).times {
+            let cc = ChunkPos::new(r.read_be_i32() as int, r.read_be_i32() as int,
+                                   r.read_be_i32() as int);
+            for (r.read_be_u32() as uint).times {
+                let local = LocalPos::new(r.read_u8() as int, r.read_u8() as int,
+                                          r.read_u8() as int);
+                let (id, data, force) = (r.read_u8(), r.read_u8(), r.read_u8() != 0);
+                match chunk::Block::from_id(id, data) {
+                    Some(b) => self.queue_write(cc, local, b, force),
+                    None => println(fmt!("unknown block %u in pending write", id as uint))
+                }
+            }
+        }
+    }
+
+    fn write_world(&self, w: @io::Writer) {
+        w.write_be_u64(self.time);
+
+        w.write_be_u32(self.pending_writes.len() as u32);
+        for self.pending_writes.each |cc, writes| {
+            w.write_be_i32(cc.x as i32);
+            w.write_be_i32(cc.y as i32);
+            w.write_be_i32(cc.z as i32);
+            w.write_be_u32(writes.len() as u32);
+            for writes.each |&(pos, block, force)| {
+                let (id, data) = block.to_id();
+                w.write_u8(pos.x as u8);
+                w.write_u8(pos.y as u8);
+                w.write_u8(pos.z as u8);
+                w.write_u8(id);
+                w.write_u8(data);
+                w.write_u8(force as u8);
+            }
+        }
+    }
 
-        self.block_at(cc)
+    fn save(&self) {
+        for self.loaded_chunks.each_key |cc| {
+            self.save_chunk(*cc);
+        }
+        os::make_dir(&path::Path(SAVE_DIR), 493); // rwxr-xr-x
+        match io::file_writer(&self.world_file_path(), [io::Create, io::Truncate]) {
+            Ok(w) => self.write_world(w),
+            Err(e) => println(fmt!("could not save world: %s", e))
+        }
     }
 
-    fn block_at(&self, cc: (int, int, int)) -> Option<&'self chunk::Block> {
-        let (x, y, z) = cc;
+    // saves the chunk along with its entities and drops it from memory
+    fn unload_chunk(&mut self, cc: ChunkPos) {
+        if !self.loaded_chunks.contains_key(&cc) { return }
+
+        self.save_chunk(cc);
+        self.loaded_chunks.remove(&cc);
+    }
+
+    /* Loads the missing chunks within LOAD_RADIUS of the chunk containing pos and unloads
+     * those further away than UNLOAD_RADIUS. Only one chunk is loaded per call, to spread the
+     * cost of generating them over several ticks.
+     */
+    fn update_loaded_chunks(&mut self, pos: &Vec3f) {
+        let center = BlockPos::from_vec(pos).chunk();
+
+        for self.chunk_coords().each |&cc| {
+            if int::abs(cc.x - center.x) > UNLOAD_RADIUS ||
+                    int::abs(cc.z - center.z) > UNLOAD_RADIUS {
+                self.unload_chunk(cc);
+            }
+        }
+
+        for int::range(-LOAD_RADIUS, LOAD_RADIUS + 1) |dx| {
+            for int::range(-LOAD_RADIUS, LOAD_RADIUS + 1) |dz| {
+                // the terrain spans the two layers of chunks either side of y = 0
+                for int::range(-1, 1) |y| {
+                    let cc = ChunkPos::new(center.x + dx, y, center.z + dz);
+                    if !self.loaded_chunks.contains_key(&cc) {
+                        self.load_chunk(cc);
+                        return
+                    }
+                }
+            }
+        }
+    }
+
+    // rebuilds the buffers of chunks whose blocks changed; the renderer calls this each frame
+    fn update_meshes(&mut self) {
+        for self.chunk_coords().each |cc| {
+            let chunk = self.loaded_chunks.find_mut(cc).unwrap();
+            if chunk.dirty { chunk.update_buffer_cache(); }
+        }
+    }
 
-        match self.loaded_chunks.find(&(div(x,16), div(y,16), div(z,16))) {
-            Some(ref chunk) => chunk.block_at((rem(x,16), rem(y,16), rem(z,16))),
+    fn chunk_coords(&self) -> ~[ChunkPos] {
+        let mut coords = ~[];
+        for self.loaded_chunks.each_key |cc| { coords.push(*cc) }
+        coords
+    }
+
+    fn block_at_vec(&self, pos: &Vec3f) -> Option<&'self chunk::Block> {
+        self.block_at(BlockPos::from_vec(pos))
+    }
+
+    fn block_at(&self, pos: BlockPos) -> Option<&'self chunk::Block> {
+        match self.loaded_chunks.find(&pos.chunk()) {
+            Some(ref chunk) => chunk.block_at(pos.local()),
+            None => None
+        }
+    }
+
+    // a copy of the block at pos, which unlike block_at leaves the world free to be changed
+    fn block(&self, pos: BlockPos) -> Option<chunk::Block> {
+        match self.block_at(pos) {
+            Some(&b) => Some(b),
             None => None
         }
     }
 
-    fn each_chunk(&self, f: &fn(&(int,int,int), &'self Chunk) -> bool) {
+    // boxes of the solid parts of blocks in the region; unloaded blocks count as solid
+    fn collision_boxes(&self, region: &Aabb) -> ~[Aabb] {
+        let mut boxes = ~[];
+        for region.each_block |pos| {
+            let height = match self.block_at(pos) {
+                Some(b) => b.collision_height(),
+                None => 1.0
+            };
+            if height > 0.0 {
+                let mut b = Aabb::block(pos);
+                b.max.y = b.min.y + height;
+                boxes.push(b);
+            }
+        }
+        boxes
+    }
+
+    fn each_chunk(&self, f: &fn(&ChunkPos, &'self Chunk) -> bool) {
         for self.loaded_chunks.each |cc, chunk| {
             if !f(cc, chunk) { return }
         }
     }
 
-    fn visit_ray(&self, origin: &Vec3f, direction: &Vec3f,
-                 f: &fn((int,int,int)) -> bool)
+    /* Visits every block the ray passes through, in order, until f returns false or the
+     * ray gets longer than max_distance. Along with each block f gets the normal of the face
+     * the ray entered it through, which is (0,0,0) for the block containing the origin, and
+     * the distance travelled to that face.
+     */
+    fn visit_ray(&self, origin: &Vec3f, direction: &Vec3f, max_distance: float,
+                 f: &fn(BlockPos, BlockPos, float) -> bool)
     {
-        let mut (x, y, z) = (origin.x, origin.y, origin.z).floor();
-        let (sgn_x, sgn_y, sgn_z) = (sgn(direction.x), sgn(direction.y), sgn(direction.z));
-        let (cb_x, cb_y, cb_z) = (x + if sgn_x > 0 {1} else {0},
-                                  y + if sgn_y > 0 {1} else {0},
-                                  z + if sgn_z > 0 {1} else {0});
-        let mut (tmax_x, tmax_y, tmax_z) = ((cb_x as float - origin.x) / direction.x,
-                                        (cb_y as float - origin.y) / direction.y,
-                                        (cb_z as float - origin.z) / direction.z);
-        if float::is_NaN(tmax_x) { tmax_x = float::infinity; }
-        if float::is_NaN(tmax_y) { tmax_y = float::infinity; }
-        if float::is_NaN(tmax_z) { tmax_z = float::infinity; }
-
-        let mut (tdelta_x, tdelta_y, tdelta_z) = (sgn_x as float / direction.x,
-                                              sgn_y as float / direction.y,
-                                              sgn_z as float / direction.z);
-        if float::is_NaN(tdelta_x) { tdelta_x = float::infinity; }
-        if float::is_NaN(tdelta_y) { tdelta_y = float::infinity; }
-        if float::is_NaN(tdelta_z) { tdelta_z = float::infinity; }
-
-        for 8.times {
-            if !f((x,y,z)) { break; }
+        let length = direction.length();
+        if length == 0.0 { return }
+        let dir = direction.div_t(length);
+
+        let start = BlockPos::from_vec(origin);
+        let mut (x, y, z) = (start.x, start.y, start.z);
+        let (sgn_x, sgn_y, sgn_z) = (sgn(dir.x), sgn(dir.y), sgn(dir.z));
+
+        // distance along the ray to the first boundary on an axis and between boundaries;
+        // an axis the ray doesn't move along is never crossed, even for -0.0
+        let boundary = |p: float, cell: int, s: int, d: float| {
+            if d == 0.0 { float::infinity }
+            else { ((cell + if s > 0 {1} else {0}) as float - p) / d }
+        };
+        let step = |d: float| {
+            if d == 0.0 { float::infinity } else { float::abs(1.0 / d) }
+        };
+
+        let mut (tmax_x, tmax_y, tmax_z) = (boundary(origin.x, x, sgn_x, dir.x),
+                                            boundary(origin.y, y, sgn_y, dir.y),
+                                            boundary(origin.z, z, sgn_z, dir.z));
+        let (tdelta_x, tdelta_y, tdelta_z) = (step(dir.x), step(dir.y), step(dir.z));
+
+        let mut normal = BlockPos::new(0, 0, 0);
+        let mut distance = 0.0;
+
+        while distance <= max_distance {
+            if !f(BlockPos::new(x, y, z), normal, distance) { break; }
 
             if tmax_x < tmax_y && tmax_x < tmax_z {
                 x += sgn_x;
+                distance = tmax_x;
                 tmax_x += tdelta_x;
+                normal = BlockPos::new(-sgn_x, 0, 0);
             } else if tmax_y < tmax_z {
                 y += sgn_y;
+                distance = tmax_y;
                 tmax_y += tdelta_y;
+                normal = BlockPos::new(0, -sgn_y, 0);
             } else {
                 z += sgn_z;
+                distance = tmax_z;
                 tmax_z += tdelta_z;
+                normal = BlockPos::new(0, 0, -sgn_z);
             }
         };
     }
 
-    fn cast_ray(&self, origin: &Vec3f, direction: &Vec3f)
-        -> Option<((int,int,int), &'self chunk::Block)>
-    {
-        for self.visit_ray(origin, direction) |pos| {
+    // finds the first solid block along the ray, skipping air and fluids
+    fn raycast(&self, origin: &Vec3f, direction: &Vec3f, max_distance: float) -> Option<RayHit> {
+        let length = direction.length();
+        if length == 0.0 { return None }
+        let dir = direction.div_t(length);
+
+        for self.visit_ray(origin, direction, max_distance) |pos, normal, distance| {
             match self.block_at(pos) {
-                Some(&chunk::Air) | None => {},
-                Some(b) => return Some((pos, b)),
+                Some(b) if *b == chunk::Air || b.is_fluid() => {},
+                None => {},
+                Some(&b) => return Some(RayHit {
+                    pos: pos,
+                    block: b,
+                    point: origin.add_v(&dir.mul_t(distance)),
+                    normal: normal,
+                    distance: distance
+                }),
             }
         };
 
         None
     }
 
-    fn cast_ray_previous(&self, origin: &Vec3f, direction: &Vec3f) ->
-        Option<((int, int, int), &'self chunk::Block)>
+    fn replace_block(&mut self, pos: BlockPos, new_block: chunk::Block)
+        -> Result<(), EditError>
     {
-        let mut prev = None;
+        if pos.y < worldgen::BEDROCK_Y || pos.y > BUILD_LIMIT { return Err(OutOfWorld) }
 
-        for self.visit_ray(origin, direction) |pos| {
-            match self.block_at(pos) {
-                None => {},
-                Some(b) if *b == chunk::Air => prev = Some((pos, b)),
-                Some(_) => return prev
+        match self.loaded_chunks.find_mut(&pos.chunk()) {
+            Some(ref chunk) => {
+                let mut block = chunk.block_at_mut(pos.local()).unwrap();
+                if block.is_protected() { return Err(Protected) }
+                *block = new_block;
+                chunk.dirty = true;
+            },
+            None => return Err(Unloaded)
+        }
+
+        if new_block.is_fluid() {
+            self.schedule_tick(pos, fluid::delay(new_block));
+        }
+        // the block itself may have been placed with nothing under it
+        if new_block.falls() || new_block.needs_support() {
+            self.block_updates.push(pos);
+        }
+        for pos.each_neighbour |n| {
+            self.block_updates.push(n);
+        }
+
+        // edits made by the update handlers join the queue instead of recursing
+        if !self.updating_blocks {
+            self.process_block_updates();
+        }
+
+        Ok(())
+    }
+
+    /* Replaces the block with air, leaving its drops behind as items if the item held by
+     * whoever broke it is good enough
+     */
+    fn break_block(&mut self, pos: BlockPos, held: Option<Item>) -> Result<(), EditError> {
+        let block = match self.block_at(pos) {
+            Some(&b) => b,
+            None => return Err(Unloaded)
+        };
+
+        match self.replace_block(pos, chunk::Air) {
+            Ok(()) if item::can_harvest(block, held) => {
+                for item::block_drops(block).each |&stack| {
+                    self.drop_item(stack, &pos.center());
+                }
+                Ok(())
+            },
+            result => result
+        }
+    }
+
+    // opens or closes the door at pos, returning false if there's no door there
+    fn toggle_door(&mut self, pos: BlockPos) -> bool {
+        let open = match self.block_at(pos) {
+            Some(&chunk::Door(open)) => open,
+            _ => return false
+        };
+        self.replace_block(pos, chunk::Door(!open)).is_ok()
+    }
+
+    /* Adds the entity to the chunk its position is in. Returns false, dropping the entity,
+     * if that chunk isn't loaded.
+     */
+    fn spawn_entity(&mut self, e: Entity) -> bool {
+        match self.loaded_chunks.find_mut(&BlockPos::from_vec(&e.position).chunk()) {
+            Some(chunk) => {
+                chunk.entities.push(e);
+                true
+            },
+            None => false
+        }
+    }
+
+    fn each_entity(&self, f: &fn(&'self Entity) -> bool) {
+        for self.loaded_chunks.each_value |chunk| {
+            for chunk.entities.each |e| {
+                if !f(e) { return }
             }
         }
+    }
 
-        None
+    // spawns an item entity near the point
+    fn drop_item(&mut self, stack: ItemStack, point: &Vec3f) {
+        let spread = Vec3f::new(self.rng.gen_float() * 0.5 - 0.25, -0.25,
+                                self.rng.gen_float() * 0.5 - 0.25);
+        self.spawn_entity(Entity::item(stack, point.add_v(&spread)));
     }
 
-    fn replace_block(&mut self, cc: (int, int, int), new_block: chunk::Block) {
-        let (x, y, z) = cc;
+    /* Offers the items that can be picked up inside the region to f, which returns how many
+     * of them it didn't take
+     */
+    fn collect_items(&mut self, region: &Aabb, f: &fn(&ItemStack) -> uint) {
+        for self.chunk_coords().each |cc| {
+            let chunk = self.loaded_chunks.find_mut(cc).unwrap();
+            for chunk.entities.each_mut |e| {
+                if e.age < entity::PICKUP_DELAY || !e.aabb().intersects(region) { loop }
+                match e.kind {
+                    ItemDrop(ref mut stack) => {
+                        let left = f(stack);
+                        stack.count = left;
+                    },
+                    _ => ()
+                }
+            }
+            remove_empty_items(&mut chunk.entities);
+        }
+    }
 
-        match self.loaded_chunks.find_mut(&(div(x,16), div(y,16), div(z, 16))) {
-            Some(ref chunk) => {
-                let mut block = chunk.block_at_mut((rem(x,16), rem(y,16), rem(z, 16))).unwrap();
-                *block = new_block;
-                chunk.update_buffer_cache();
+    /* Like replace_block, but edits in unloaded chunks are applied once the chunk loads. If
+     * force is false they only fill the block if it's air by then, see worldgen::apply_write.
+     */
+    fn replace_block_queued(&mut self, pos: BlockPos, new_block: chunk::Block, force: bool)
+        -> Result<(), EditError>
+    {
+        match self.replace_block(pos, new_block) {
+            Err(Unloaded) => {
+                self.queue_write(pos.chunk(), pos.local(), new_block, force);
+                Ok(())
             },
-            None => fail!(~"replace_block in unloaded chunk")
+            result => result
+        }
+    }
+
+    fn queue_write(&mut self, chunk: ChunkPos, local: LocalPos,
+                   block: chunk::Block, force: bool)
+    {
+        if !self.pending_writes.contains_key(&chunk) {
+            self.pending_writes.insert(chunk, ~[]);
+        }
+        self.pending_writes.find_mut(&chunk).unwrap().push((local, block, force));
+    }
+
+    /* Handles queued neighbour updates. A cascade that keeps producing updates is cut off
+     * after MAX_BLOCK_UPDATES and continued on the next tick.
+     */
+    fn process_block_updates(&mut self) {
+        self.updating_blocks = true;
+
+        let mut budget = MAX_BLOCK_UPDATES;
+        while budget > 0 && self.block_updates.len() > 0 {
+            let pos = self.block_updates.shift();
+            self.neighbour_changed(pos);
+            budget -= 1;
+        }
+
+        self.updating_blocks = false;
+    }
+
+    fn neighbour_changed(&mut self, pos: BlockPos) {
+        let block = match self.block_at(pos) {
+            Some(&b) => b,
+            None => return
+        };
+
+        if block.is_fluid() {
+            self.schedule_tick(pos, fluid::delay(block));
+        }
+
+        let supported = match self.block_at(pos.down()) {
+            Some(b) => b.blocks(),
+            None => true
+        };
+
+        if block.needs_support() && !supported {
+            self.break_block(pos, None);
+        }
+
+        if block.falls() && !supported && self.replace_block(pos, chunk::Air).is_ok() {
+            self.spawn_entity(Entity::falling_block(block, pos));
+        }
+    }
+
+    /* Ticks the entities of every loaded chunk and moves those that crossed into another
+     * chunk over to it. Entities heading into an unloaded chunk stay where they were until
+     * it loads.
+     */
+    fn tick_entities(&mut self, player: &mut Player, dt: float) {
+        let mut entities = ~[];
+        for self.chunk_coords().each |&cc| {
+            let chunk = self.loaded_chunks.find_mut(&cc).unwrap();
+            for util::replace(&mut chunk.entities, ~[]).consume |_, e| {
+                entities.push((cc, e));
+            }
+        }
+
+        let mut actions = ~[];
+        for uint::range(0, entities.len()) |i| {
+            let (_, ref mut e) = entities[i];
+            actions.push(e.tick(self, &*player, dt));
         }
+
+        // the entities that stay, with the chunk each came from
+        let mut kept = ~[];
+        let mut homes = ~[];
+        let mut placed = ~[];
+        for entities.consume |i, entry| {
+            let (from, e) = entry;
+            let keep = match actions[i] {
+                entity::Keep => true,
+                entity::Remove => false,
+                entity::PlaceBlock(pos, block) => {
+                    placed.push((pos, block));
+                    false
+                },
+                entity::Attack(damage) => {
+                    player.damage(damage);
+                    true
+                }
+            };
+            if keep {
+                kept.push(e);
+                homes.push(from);
+            }
+        }
+
+        // merging before the entities are split up again lets items join across chunk borders
+        merge_items(kept);
+        for kept.consume |i, e| {
+            if is_empty_item(&e) { loop }
+            let to = BlockPos::from_vec(&e.position).chunk();
+            let cc = if self.loaded_chunks.contains_key(&to) { to } else { homes[i] };
+            self.loaded_chunks.find_mut(&cc).unwrap().entities.push(e);
+        }
+
+        // placing blocks can spawn more entities, so that's left until they're all back;
+        // blocks landing where they can't be placed break into their drops, and so do blocks
+        // like torches and crops that they land on
+        for placed.each |&(pos, block)| {
+            let existing = match self.block_at(pos) {
+                Some(&b) => b,
+                None => chunk::Air
+            };
+            let broken = if existing.blocks() ||
+                    self.replace_block_queued(pos, block, false).is_err() {
+                block
+            } else {
+                existing
+            };
+            for item::block_drops(broken).each |&stack| {
+                self.drop_item(stack, &pos.center());
+            }
+        }
+    }
+
+    // runs the block's scheduled update after the given number of ticks
+    fn schedule_tick(&mut self, pos: BlockPos, delay: u64) {
+        let due = self.ticks + delay;
+
+        match self.loaded_chunks.find_mut(&pos.chunk()) {
+            Some(chunk) => chunk.schedule_tick(pos.local(), due),
+            None => ()
+        }
+    }
+
+    // advances the world by one tick, TICKS_PER_SECOND times a second
+    fn tick(&mut self, player: &mut Player) {
+        self.ticks += 1;
+        self.time += 1;
+        let now = self.ticks;
+
+        self.update_loaded_chunks(&player.position);
+        self.process_block_updates();
+        self.tick_entities(player, 1.0 / TICKS_PER_SECOND);
+        mob::spawn_mobs(self, &*player);
+
+        let mut due = ~[];
+        for self.chunk_coords().each |&cc| {
+            let chunk = self.loaded_chunks.find_mut(&cc).unwrap();
+            for chunk.take_due_ticks(now).each |&local| {
+                due.push(cc.block(local));
+            }
+        }
+        for due.each |&pos| {
+            self.scheduled_tick(pos);
+        }
+
+        for self.chunk_coords().each |&cc| {
+            for RANDOM_TICKS_PER_CHUNK.times {
+                let local = LocalPos::new(self.rng.gen_int_range(0, CHUNK_SIZE),
+                                          self.rng.gen_int_range(0, CHUNK_SIZE),
+                                          self.rng.gen_int_range(0, CHUNK_SIZE));
+                self.random_tick(cc.block(local));
+            }
+        }
+    }
+
+    fn scheduled_tick(&mut self, pos: BlockPos) {
+        let block = match self.block_at(pos) {
+            Some(&b) => b,
+            None => return
+        };
+
+        match block {
+            chunk::Water(_) | chunk::Lava(_) => fluid::update(self, pos),
+            _ => ()
+        }
+    }
+
+    fn random_tick(&mut self, pos: BlockPos) {
+        let block = match self.block_at(pos) {
+            Some(&b) => b,
+            None => return
+        };
+
+        match block {
+            chunk::Grass => plants::grass_tick(self, pos),
+            chunk::Wheat(_) => plants::crop_tick(self, pos),
+            _ => ()
+        }
+    }
+}
+
+/* Joins item entities of the same item lying close to each other. The stacks merged away are
+ * left with a count of zero for the caller to remove.
+ */
+fn merge_items(entities: &mut [Entity]) {
+    let n = entities.len();
+    for uint::range(0, n) |i| {
+        for uint::range(i + 1, n) |j| {
+            let d = entities[i].position.sub_v(&entities[j].position).length();
+            if d > ITEM_MERGE_DISTANCE { loop }
+
+            let merged = match (&entities[i].kind, &entities[j].kind) {
+                (&ItemDrop(a), &ItemDrop(b)) if a.count > 0 && b.count > 0 &&
+                        a.item == b.item && a.count + b.count <= a.item.max_stack() =>
+                    Some((a, b)),
+                _ => None
+            };
+            match merged {
+                Some((a, b)) => {
+                    entities[i].kind = ItemDrop(ItemStack { item: a.item,
+                                                            count: a.count + b.count });
+                    entities[j].kind = ItemDrop(ItemStack { item: b.item, count: 0 });
+                },
+                None => ()
+            }
+        }
+    }
+}
+
+fn is_empty_item(e: &Entity) -> bool {
+    match e.kind {
+        ItemDrop(ref stack) => stack.count == 0,
+        _ => false
+    }
+}
+
+fn remove_empty_items(entities: &mut ~[Entity]) {
+    entities.retain(|e| !is_empty_item(e));
+}
+
+#[cfg(test)]
+mod tests {
+    use chunk;
+    use config::Config;
+    use coords::{BlockPos, ChunkPos};
+    use entity::{Entity, ItemDrop};
+    use hunger::HungerConfig;
+    use item::{BlockItem, ItemStack};
+    use player::{Player, Speeds};
+    use world;
+    use world::World;
+
+    use common::*;
+    use core::float;
+
+    fn close(a: float, b: float) -> bool {
+        float::abs(a - b) < 1e-9
+    }
+
+    // every block, normal and distance the ray visits
+    fn visited(origin: &Vec3f, dir: &Vec3f, max_distance: float)
+        -> ~[(BlockPos, BlockPos, float)]
+    {
+        let world = World::empty();
+        let mut cells = ~[];
+        for world.visit_ray(origin, dir, max_distance) |pos, normal, distance| {
+            cells.push((pos, normal, distance));
+        }
+        cells
+    }
+
+    #[test]
+    fn rays_along_each_axis() {
+        let origin = Vec3f::new(8.5, 8.5, 8.5);
+        let start = BlockPos::new(8, 8, 8);
+        for [1.0, -1.0].each |&s| {
+            for [Vec3f::new(s, 0.0, 0.0), Vec3f::new(0.0, s, 0.0),
+                 Vec3f::new(0.0, 0.0, s)].each |&dir| {
+                let step = BlockPos::from_vec(&dir);
+
+                let cells = visited(&origin, &dir.mul_t(3.0), 3.0);
+                // boundaries are half a block away, then one block apart; 3.5 is too far
+                assert_eq!(cells.len(), 4);
+                for cells.eachi |i, &(pos, normal, distance)| {
+                    assert!(pos == start + BlockPos::new(step.x * i as int, step.y * i as int,
+                                                         step.z * i as int));
+                    if i == 0 {
+                        assert!(normal == BlockPos::new(0, 0, 0));
+                        assert_eq!(distance, 0.0);
+                    } else {
+                        assert!(normal == -step);
+                        assert!(close(distance, i as float - 0.5));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ray_with_one_zero_component() {
+        // crosses the x and y boundaries at the same distance, and never moves along z
+        let cells = visited(&Vec3f::new(8.5, 8.5, 8.5), &Vec3f::new(1.0, 1.0, 0.0), 1.0);
+        let d = float::sqrt(0.5);
+        assert_eq!(cells.len(), 3);
+        let (pos, normal, distance) = cells[0];
+        assert!(pos == BlockPos::new(8, 8, 8) && normal == BlockPos::new(0, 0, 0));
+        assert_eq!(distance, 0.0);
+        let (pos, normal, distance) = cells[1];
+        assert!(pos == BlockPos::new(8, 9, 8) && normal == BlockPos::new(0, -1, 0));
+        assert!(close(distance, d));
+        let (pos, normal, distance) = cells[2];
+        assert!(pos == BlockPos::new(9, 9, 8) && normal == BlockPos::new(-1, 0, 0));
+        assert!(close(distance, d));
+    }
+
+    #[test]
+    fn negative_zero_components_are_never_crossed() {
+        let cells = visited(&Vec3f::new(8.5, 8.5, 8.5), &Vec3f::new(-0.0, -0.0, -1.0), 5.0);
+        assert_eq!(cells.len(), 6);
+        for cells.eachi |i, &(pos, _, _)| {
+            assert!(pos == BlockPos::new(8, 8, 8 - i as int));
+        }
+    }
+
+    #[test]
+    fn zero_direction_visits_nothing() {
+        assert!(visited(&Vec3f::new(8.5, 8.5, 8.5), &Vec3f::zero(), 5.0).is_empty());
+    }
+
+    #[test]
+    fn raycast_hits_the_face_towards_the_origin() {
+        // the ray starts in the middle of a chunk of air
+        let mut world = world::test_world(world::new_empty_chunk(), []);
+        assert!(world.replace_block(BlockPos::new(8, 8, 11), chunk::Stone).is_ok());
+        let origin = Vec3f::new(8.5, 8.5, 8.5);
+
+        match world.raycast(&origin, &Vec3f::new(0.0, 0.0, 2.0), 5.0) {
+            Some(hit) => {
+                assert!(hit.pos == BlockPos::new(8, 8, 11));
+                assert!(hit.normal == BlockPos::new(0, 0, -1));
+                assert!(close(hit.distance, 2.5));
+                assert!(close(hit.point.z, 11.0));
+                assert!(hit.block == chunk::Stone);
+            },
+            None => fail!(~"the ray missed the block")
+        }
+
+        // the block is 2.5 away
+        assert!(world.raycast(&origin, &Vec3f::new(0.0, 0.0, 1.0), 2.0).is_none());
+        assert!(world.raycast(&origin, &Vec3f::new(0.0, 0.0, -1.0), 5.0).is_none());
+    }
+
+    #[test]
+    fn raycast_skips_fluids() {
+        // the ray starts in the middle of a chunk of air
+        let mut world = world::test_world(world::new_empty_chunk(), []);
+        assert!(world.replace_block(BlockPos::new(9, 8, 8), chunk::Water(0)).is_ok());
+        assert!(world.replace_block(BlockPos::new(10, 8, 8), chunk::Dirt).is_ok());
+
+        match world.raycast(&Vec3f::new(8.5, 8.5, 8.5), &Vec3f::new(1.0, 0.0, 0.0), 5.0) {
+            Some(hit) => {
+                assert!(hit.pos == BlockPos::new(10, 8, 8));
+                assert!(hit.normal == BlockPos::new(-1, 0, 0));
+            },
+            None => fail!(~"the ray missed the block")
+        }
+    }
+
+    // a player out of the way of the entities being tested
+    fn player() -> Player {
+        let config = Config::new();
+        Player::new(Vec3f::new(8.5, 1.0, 0.5), Speeds::from_config(&config),
+                    HungerConfig::from_config(&config))
+    }
+
+    #[test]
+    fn items_merge_across_chunk_borders() {
+        let mut w = world::test_world(world::new_test_chunk(), []);
+        w.set_chunk(ChunkPos::new(1, 0, 0), world::new_test_chunk());
+        let stack = ItemStack { item: BlockItem(chunk::Dirt), count: 1 };
+        w.spawn_entity(Entity::new(ItemDrop(stack), Vec3f::new(15.8, 1.0, 8.5)));
+        w.spawn_entity(Entity::new(ItemDrop(stack), Vec3f::new(16.2, 1.0, 8.5)));
+
+        w.tick_entities(&mut player(), 0.05);
+
+        let mut counts = ~[];
+        for w.each_entity |e| {
+            match e.kind {
+                ItemDrop(ref stack) => counts.push(stack.count),
+                _ => ()
+            }
+        }
+        assert_eq!(counts, ~[2]);
+    }
+
+    #[test]
+    fn queued_edits_apply_when_the_chunk_loads() {
+        let mut w = World::empty();
+        // just above the bedrock, far from anything saved or any feature
+        let pos = BlockPos::new(16008, -15, 16008);
+        assert!(w.replace_block_queued(pos, chunk::Planks, true).is_ok());
+        assert!(w.block_at(pos).is_none());
+
+        w.load_chunk(pos.chunk());
+        assert!(w.block_at(pos) == Some(&chunk::Planks));
+    }
+
+    #[test]
+    fn falling_blocks_break_what_they_land_in() {
+        let mut w = world::test_world(world::new_test_chunk(), []);
+        // a torch on the floor with sand coming down on it
+        let pos = BlockPos::new(8, 1, 8);
+        assert!(w.replace_block(pos, chunk::Torch).is_ok());
+        w.spawn_entity(Entity::falling_block(chunk::Sand, pos));
+
+        w.tick_entities(&mut player(), 0.05);
+
+        assert!(w.block_at(pos) == Some(&chunk::Sand));
+        let mut drops = ~[];
+        for w.each_entity |e| {
+            match e.kind {
+                ItemDrop(ref stack) => drops.push(stack.item),
+                _ => ()
+            }
+        }
+        assert!(drops == ~[BlockItem(chunk::Torch)]);
+    }
+
+    #[test]
+    fn unsupported_blocks_break_when_placed() {
+        let mut w = world::test_world(world::new_test_chunk(), []);
+        let pos = BlockPos::new(8, 3, 8);
+        assert!(w.replace_block(pos, chunk::Torch).is_ok());
+        assert!(w.block_at(pos) == Some(&chunk::Air));
+        assert!(w.replace_block(pos.down().down(), chunk::Torch).is_ok());
+        assert!(w.block_at(pos.down().down()) == Some(&chunk::Torch));
     }
 }
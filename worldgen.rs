@@ -0,0 +1,438 @@
+use chunk;
+use chunk::Chunk;
+use coords::{BlockPos, ChunkPos, LocalPos};
+use noise::{Noise2DContext, Noise3DContext};
+use core::rand::{Rng, RngUtil};
+use core::float;
+
+// world y of the bedrock layer; nothing below it is ever carved
+pub static BEDROCK_Y: int = -16;
+
+// low terrain is flooded up to this world y
+pub static SEA_LEVEL: int = -14;
+
+// distance a cave worm advances per step, in blocks
+static WORM_STEP: float = 1.5;
+
+pub struct OreConfig {
+    block: chunk::Block,
+    // inclusive world y range the veins may start in
+    min_y: int,
+    max_y: int,
+    vein_size: uint,
+    veins_per_chunk: uint
+}
+
+pub static ores: [OreConfig, ..5] = [
+    OreConfig { block: chunk::CoalOre,    min_y: -16, max_y: -1,  vein_size: 12, veins_per_chunk: 6 },
+    OreConfig { block: chunk::IronOre,    min_y: -16, max_y: -6,  vein_size: 8,  veins_per_chunk: 4 },
+    OreConfig { block: chunk::GoldOre,    min_y: -16, max_y: -11, vein_size: 6,  veins_per_chunk: 2 },
+    OreConfig { block: chunk::DiamondOre, min_y: -16, max_y: -13, vein_size: 4,  veins_per_chunk: 1 },
+    // not an ore, but spread through the stone the same way
+    OreConfig { block: chunk::Gravel,     min_y: -16, max_y: -1,  vein_size: 16, veins_per_chunk: 2 },
+];
+
+/* Small hand-built structures, one string per row from north to south and one array per
+ * layer from the ground up. '#' is cobblestone, 'p' planks, 'w' wood, 's' a slab, '.' forces air and
+ * ' ' leaves whatever was generated there.
+ */
+pub struct Template {
+    layers: &'static [&'static [&'static str]]
+}
+
+pub static well: Template = Template { layers: &[
+    &["#####", "#...#", "#...#", "#...#", "#####"],
+    &["#####", "#...#", "#...#", "#...#", "#####"],
+    &["w   w", "     ", "     ", "     ", "w   w"],
+    &["w   w", "     ", "     ", "     ", "w   w"],
+    &["ppppp", "ppppp", "ppppp", "ppppp", "ppppp"],
+]};
+
+pub static ruin: Template = Template { layers: &[
+    &["#####", "#ppp#", "#ppp#", "#ppp#", "#####"],
+    &["#.#.#", ".....", "#...#", ".....", "##.##"],
+    &["#s  #", "s    ", "     ", "     ", "#  s#"],
+]};
+
+// A block written by a feature, in world coordinates
+pub struct FeatureWrite {
+    pos: BlockPos,
+    block: chunk::Block,
+    // overwrite generated terrain instead of only filling air
+    force: bool
+}
+
+pub struct CaveConfig {
+    // 3D noise values above this become cavern space
+    cavern_threshold: f32,
+    // upper bound of worms started from each chunk column
+    worms_per_column: uint,
+    worm_length: uint,
+    worm_radius: float,
+    // carved blocks at or below the given world y are filled with the fluid instead of air
+    fluid: Option<(chunk::Block, int)>
+}
+
+pub impl CaveConfig {
+    fn default() -> CaveConfig {
+        CaveConfig {
+            cavern_threshold: 0.45,
+            worms_per_column: 2,
+            worm_length: 40,
+            worm_radius: 1.6,
+            fluid: Some((chunk::Lava(0), BEDROCK_Y + 2))
+        }
+    }
+}
+
+pub struct Generator {
+    seed: uint,
+    height: Noise2DContext,
+    caverns: Noise3DContext,
+    caves: CaveConfig
+}
+
+pub impl Generator {
+    fn new(seed: uint) -> Generator {
+        Generator {
+            seed: seed,
+            height: Noise2DContext::new(seed),
+            caverns: Noise3DContext::new(seed),
+            caves: CaveConfig::default()
+        }
+    }
+
+    fn generate(&self, cc: ChunkPos) -> Chunk {
+        let mut c = self.terrain(cc);
+        self.carve_caves(&mut c, cc);
+        self.place_ores(&mut c, cc);
+        c
+    }
+
+    /* An rng that only depends on the world seed and the given coordinates, so that
+     * every stage produces the same result no matter in which order chunks are generated
+     */
+    fn rng_at(&self, x: int, y: int, z: int, salt: uint) -> @Rng {
+        let h = self.seed ^ (x as uint * 73856093) ^ (y as uint * 19349663)
+                          ^ (z as uint * 83492791) ^ (salt * 2654435761);
+        rand::seeded_rng(&[h as u8, (h >> 8) as u8, (h >> 16) as u8, (h >> 24) as u8,
+                           (h >> 32) as u8, (h >> 40) as u8, (h >> 48) as u8, (h >> 56) as u8])
+    }
+
+    fn terrain(&self, cc: ChunkPos) -> Chunk {
+        let (cx, cy, cz) = (cc.x, cc.y, cc.z);
+        let mut c = Chunk::new();
+        for c.each_block_mut |(x,y,z), block| {
+            let wy = cy * 16 + y as int;
+            // height above bedrock, which is the local y inside the surface chunk
+            let ly = wy - BEDROCK_Y;
+            let h = self.height.get((cx * 16 + x as int) as f32 * 0.1,
+                                    (cz * 16 + z as int) as f32 * 0.1);
+            *block = if ly <= 0 {
+                chunk::Bedrock
+            } else if ly as f32 / 6.0 < h {
+                if ly >= 5 { chunk::Stone }
+                else if wy <= SEA_LEVEL + 1 { chunk::Sand }
+                else { chunk::Grass }
+            } else if wy <= SEA_LEVEL {
+                chunk::Water(0)
+            } else { chunk::Air };
+        };
+        c
+    }
+
+    fn carvable(&self, block: chunk::Block, wy: int) -> bool {
+        wy > BEDROCK_Y && match block {
+            chunk::Air | chunk::Bedrock | chunk::Water(_) | chunk::Lava(_) => false,
+            _ => true
+        }
+    }
+
+    fn carved_block(&self, wy: int) -> chunk::Block {
+        match self.caves.fluid {
+            Some((fluid, level)) if wy <= level => fluid,
+            _ => chunk::Air
+        }
+    }
+
+    fn carve_caves(&self, c: &mut Chunk, cc: ChunkPos) {
+        let (cx, cy, cz) = (cc.x, cc.y, cc.z);
+
+        for c.each_block_mut |(x,y,z), block| {
+            let (wx, wy, wz) = (cx * 16 + x as int, cy * 16 + y as int, cz * 16 + z as int);
+            if !self.carvable(*block, wy) { loop }
+
+            // squashed vertically so caverns are wider than they are tall
+            let n = self.caverns.get(wx as f32 * 0.06, wy as f32 * 0.1, wz as f32 * 0.06);
+            if n > self.caves.cavern_threshold {
+                *block = self.carved_block(wy);
+            }
+        }
+
+        // worms may start in any column close enough to reach into this chunk
+        let reach = float::ceil((self.caves.worm_length as float * WORM_STEP / 16.0) as f64) as int;
+        for int::range(cx - reach, cx + reach + 1) |ox| {
+            for int::range(cz - reach, cz + reach + 1) |oz| {
+                self.carve_worms_from(ox, oz, c, cc);
+            }
+        }
+    }
+
+    fn carve_worms_from(&self, ox: int, oz: int, c: &mut Chunk, cc: ChunkPos) {
+        let r = self.rng_at(ox, 0, oz, 1);
+        let pi = float::consts::pi;
+
+        for r.gen_uint_range(0, self.caves.worms_per_column + 1).times {
+            let mut x = (ox * 16) as float + r.gen_float() * 16.0;
+            let mut y = (BEDROCK_Y + 3) as float + r.gen_float() * 10.0;
+            let mut z = (oz * 16) as float + r.gen_float() * 16.0;
+            let mut yaw = r.gen_float() * pi * 2.0;
+            let mut pitch = (r.gen_float() - 0.5) * 0.5;
+
+            for self.caves.worm_length.times {
+                let radius = self.caves.worm_radius * (0.75 + r.gen_float() * 0.5);
+                self.carve_sphere(c, cc, x, y, z, radius);
+
+                x += float::cos(yaw) * float::cos(pitch) * WORM_STEP;
+                y += float::sin(pitch) * WORM_STEP;
+                z += float::sin(yaw) * float::cos(pitch) * WORM_STEP;
+                yaw += (r.gen_float() - 0.5) * 0.6;
+                pitch = pitch * 0.7 + (r.gen_float() - 0.5) * 0.4;
+            }
+        }
+    }
+
+    fn carve_sphere(&self, c: &mut Chunk, cc: ChunkPos,
+                    x: float, y: float, z: float, radius: float)
+    {
+        let (cx, cy, cz) = (cc.x, cc.y, cc.z);
+        let (ox, oy, oz) = ((cx * 16) as float, (cy * 16) as float, (cz * 16) as float);
+
+        if x + radius < ox || x - radius > ox + 16.0 ||
+           y + radius < oy || y - radius > oy + 16.0 ||
+           z + radius < oz || z - radius > oz + 16.0 { return }
+
+        for c.each_block_mut |(bx,by,bz), block| {
+            let (dx, dy, dz) = (ox + bx as float + 0.5 - x,
+                                oy + by as float + 0.5 - y,
+                                oz + bz as float + 0.5 - z);
+            if dx*dx + dy*dy + dz*dz > radius*radius { loop }
+
+            let wy = cy * 16 + by as int;
+            if self.carvable(*block, wy) {
+                *block = self.carved_block(wy);
+            }
+        }
+    }
+
+    // world y of the topmost terrain block in the column, before caves are carved
+    fn surface_height(&self, wx: int, wz: int) -> int {
+        let h = self.height.get(wx as f32 * 0.1, wz as f32 * 0.1);
+        BEDROCK_Y + int::max(0, f32::ceil(h * 6.0) as int - 1)
+    }
+
+    /* Features are rooted on the surface of this chunk but may extend into any of its
+     * neighbours, so they are returned as world space writes for the world to distribute.
+     */
+    fn features(&self, c: &Chunk, cc: ChunkPos) -> ~[FeatureWrite] {
+        let (cx, cy, cz) = (cc.x, cc.y, cc.z);
+        let r = self.rng_at(cx, cy, cz, 3);
+        let mut writes = ~[];
+
+        // picks a random column and returns its surface if it lies in this chunk
+        let pick_surface = || {
+            let (x, z) = (r.gen_int_range(0, 16), r.gen_int_range(0, 16));
+            let wy = self.surface_height(cx * 16 + x, cz * 16 + z);
+            if wy >= cy * 16 && wy < cy * 16 + 16 {
+                Some(BlockPos::new(cx * 16 + x, wy, cz * 16 + z))
+            } else { None }
+        };
+        let block_at = |pos: BlockPos| *c.block_at(pos.local()).unwrap();
+
+        for r.gen_uint_range(0, 4).times {
+            match pick_surface() {
+                Some(pos) if block_at(pos) == chunk::Grass => {
+                    let height = r.gen_int_range(4, 7);
+                    self.tree(&mut writes, pos, height);
+                },
+                _ => ()
+            }
+        }
+
+        if r.gen_uint_range(0, 4) == 0 {
+            match pick_surface() {
+                Some(pos) if block_at(pos) != chunk::Air => {
+                    let radius = 1.0 + r.gen_float();
+                    self.boulder(&mut writes, pos, radius);
+                },
+                _ => ()
+            }
+        }
+
+        // wild wheat, where the first seeds come from
+        if r.gen_uint_range(0, 3) == 0 {
+            match pick_surface() {
+                Some(pos) if block_at(pos) == chunk::Grass => {
+                    let count = r.gen_uint_range(3, 7);
+                    self.wheat_patch(&mut writes, pos, count, r);
+                },
+                _ => ()
+            }
+        }
+
+        if r.gen_uint_range(0, 16) == 0 {
+            match pick_surface() {
+                Some(pos) if block_at(pos) == chunk::Grass => {
+                    let template = if r.gen_uint_range(0, 2) == 0 { &well } else { &ruin };
+                    self.structure(&mut writes, pos, template);
+                },
+                _ => ()
+            }
+        }
+
+        writes
+    }
+
+    fn tree(&self, writes: &mut ~[FeatureWrite], ground: BlockPos, height: int) {
+        let top = ground.y + height;
+
+        for int::range(top - 2, top + 2) |ly| {
+            let radius = if ly >= top { 1 } else { 2 };
+            for int::range(-radius, radius + 1) |dx| {
+                for int::range(-radius, radius + 1) |dz| {
+                    // leave out the corners so the crown looks a bit rounder
+                    if int::abs(dx) == radius && int::abs(dz) == radius { loop }
+                    writes.push(FeatureWrite { pos: BlockPos::new(ground.x + dx, ly, ground.z + dz),
+                                               block: chunk::Leaves, force: false });
+                }
+            }
+        }
+        for int::range(ground.y + 1, top) |ly| {
+            writes.push(FeatureWrite { pos: BlockPos::new(ground.x, ly, ground.z),
+                                       block: chunk::Wood, force: false });
+        }
+        writes.push(FeatureWrite { pos: ground, block: chunk::Dirt, force: true });
+    }
+
+    // ripe wheat on the terrain around ground; stalks that miss the soil pop off when ticked
+    fn wheat_patch(&self, writes: &mut ~[FeatureWrite], ground: BlockPos, count: uint, r: @Rng) {
+        for count.times {
+            let (x, z) = (ground.x + r.gen_int_range(-2, 3), ground.z + r.gen_int_range(-2, 3));
+            writes.push(FeatureWrite { pos: BlockPos::new(x, self.surface_height(x, z) + 1, z),
+                                       block: chunk::Wheat(7), force: false });
+        }
+    }
+
+    fn boulder(&self, writes: &mut ~[FeatureWrite], ground: BlockPos, radius: float) {
+        let r = float::ceil(radius as f64) as int;
+
+        for int::range(-r, r + 1) |dx| {
+            for int::range(-r, r + 1) |dy| {
+                for int::range(-r, r + 1) |dz| {
+                    if (dx*dx + dy*dy + dz*dz) as float > radius * radius { loop }
+                    writes.push(FeatureWrite { pos: ground.offset(dx, dy, dz),
+                                               block: chunk::Cobblestone, force: dy <= 0 });
+                }
+            }
+        }
+    }
+
+    fn structure(&self, writes: &mut ~[FeatureWrite], ground: BlockPos,
+                 template: &Template)
+    {
+        for template.layers.eachi |ly, layer| {
+            for layer.eachi |lz, row| {
+                for row.each_chari |lx, ch| {
+                    let block = match ch {
+                        '#' => chunk::Cobblestone,
+                        'p' => chunk::Planks,
+                        'w' => chunk::Wood,
+                        's' => chunk::Slab,
+                        '.' => chunk::Air,
+                        _ => loop
+                    };
+                    writes.push(FeatureWrite { pos: ground.offset(lx as int, ly as int, lz as int),
+                                               block: block, force: true });
+                }
+            }
+        }
+    }
+
+    fn place_ores(&self, c: &mut Chunk, cc: ChunkPos) {
+        let (cx, cy, cz) = (cc.x, cc.y, cc.z);
+        let r = self.rng_at(cx, cy, cz, 2);
+
+        for ores.each |ore| {
+            let lo = int::max(ore.min_y, cy * 16);
+            let hi = int::min(ore.max_y, cy * 16 + 15);
+            if lo > hi { loop }
+
+            for ore.veins_per_chunk.times {
+                let mut (x, y, z) = (r.gen_int_range(0, 16), r.gen_int_range(lo, hi + 1) - cy * 16,
+                                     r.gen_int_range(0, 16));
+
+                // a random walk that stays inside this chunk and only replaces stone
+                for ore.vein_size.times {
+                    match c.block_at_mut(LocalPos::new(x, y, z)) {
+                        Some(b) => if *b == chunk::Stone { *b = ore.block },
+                        None => ()
+                    }
+                    match r.gen_uint_range(0, 6) {
+                        0 => x += 1, 1 => x -= 1,
+                        2 => y += 1, 3 => y -= 1,
+                        4 => z += 1, _ => z -= 1
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Applies a feature write to the chunk containing it; protected blocks are never replaced
+pub fn apply_write(c: &mut Chunk, pos: LocalPos, block: chunk::Block, force: bool) {
+    match c.block_at_mut(pos) {
+        Some(b) if !b.is_protected() => {
+            if force || *b == chunk::Air || (*b == chunk::Leaves && block != chunk::Leaves) {
+                *b = block
+            }
+        },
+        _ => ()
+    }
+}
+
+pub fn ore_counts(c: &Chunk) -> ~[uint] {
+    let mut counts = vec::from_elem(ores.len(), 0u);
+    for c.each_block |_, block| {
+        for ores.eachi |i, ore| {
+            if *block == ore.block { counts[i] += 1 }
+        }
+    }
+    counts
+}
+
+// Prints ore counts for every underground chunk within radius of the origin, for balance tuning
+pub fn print_ore_stats(seed: uint, radius: int) {
+    let gen = Generator::new(seed);
+    let mut totals = vec::from_elem(ores.len(), 0u);
+    let mut chunks = 0u;
+
+    for int::range(-radius, radius + 1) |cx| {
+        for int::range(-radius, radius + 1) |cz| {
+            for int::range(BEDROCK_Y / 16, 0) |cy| {
+                let counts = ore_counts(&gen.generate(ChunkPos::new(cx, cy, cz)));
+                let mut line = fmt!("chunk (%d, %d, %d):", cx, cy, cz);
+                for ores.eachi |i, ore| {
+                    line.push_str(fmt!(" %s %u", ore.block.name(), counts[i]));
+                    totals[i] += counts[i];
+                }
+                println(line);
+                chunks += 1;
+            }
+        }
+    }
+
+    for ores.eachi |i, ore| {
+        println(fmt!("%s: %u total, %f per chunk", ore.block.name(), totals[i],
+                     totals[i] as float / chunks as float));
+    }
+}